@@ -0,0 +1,56 @@
+//! Compares eager and lazy context in a parse loop where 99 out of every 100
+//! failures are recovered from without being displayed.
+#[macro_use]
+extern crate bencher;
+extern crate failure;
+
+use bencher::{black_box, Bencher};
+use failure::ResultExt;
+
+const LINES: usize = 1000;
+
+// The input is leaked so that both variants capture the same `&'static`
+// borrow of each line, and only differ in when the message is formatted.
+fn input() -> &'static [String] {
+    let input: Vec<String> = (0..LINES).map(|i| format!("value-{}", i)).collect();
+    Box::leak(input.into_boxed_slice())
+}
+
+fn eager(bench: &mut Bencher) {
+    let input = input();
+    bench.iter(|| {
+        let mut reported = 0;
+        for (line, value) in input.iter().enumerate() {
+            let result = value
+                .parse::<u32>()
+                .with_context(|_| format!("invalid number {:?} on line {}", value, line));
+            if let Err(err) = result {
+                if line % 100 == 0 {
+                    reported += err.to_string().len();
+                }
+            }
+        }
+        black_box(reported)
+    });
+}
+
+fn lazy(bench: &mut Bencher) {
+    let input = input();
+    bench.iter(|| {
+        let mut reported = 0;
+        for (line, value) in input.iter().enumerate() {
+            let result = value
+                .parse::<u32>()
+                .lazy_context(move |f| write!(f, "invalid number {:?} on line {}", value, line));
+            if let Err(err) = result {
+                if line % 100 == 0 {
+                    reported += err.to_string().len();
+                }
+            }
+        }
+        black_box(reported)
+    });
+}
+
+benchmark_group!(benches, eager, lazy);
+benchmark_main!(benches);
@@ -0,0 +1,33 @@
+//! Compares wrapping an `Error` five times in nested contexts with pushing
+//! the same contexts onto its context stack with `Error::wrap`.
+#[macro_use]
+extern crate bencher;
+extern crate failure;
+
+use bencher::{black_box, Bencher};
+use failure::{err_msg, Error};
+
+const CONTEXTS: [&str; 5] = ["parsing", "reading", "loading", "starting", "running"];
+
+fn nested(bench: &mut Bencher) {
+    bench.iter(|| {
+        let mut error = err_msg("invalid digit");
+        for context in &CONTEXTS {
+            error = Error::from(error.context(*context));
+        }
+        black_box(error)
+    });
+}
+
+fn wrap(bench: &mut Bencher) {
+    bench.iter(|| {
+        let mut error = err_msg("invalid digit");
+        for context in &CONTEXTS {
+            error = error.wrap(*context);
+        }
+        black_box(error)
+    });
+}
+
+benchmark_group!(benches, nested, wrap);
+benchmark_main!(benches);
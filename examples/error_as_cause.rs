@@ -1,4 +1,3 @@
-#[macro_use]
 extern crate failure;
 
 use failure::{err_msg, Error, Fail};
@@ -12,7 +11,7 @@ fn bad_function() -> Result<(), WrappingError> {
 }
 
 fn main() {
-    for cause in Fail::iter_causes(&bad_function().unwrap_err()) {
+    for cause in <dyn Fail>::iter_causes(&bad_function().unwrap_err()) {
         println!("{}", cause);
     }
 }
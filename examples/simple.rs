@@ -1,4 +1,3 @@
-#[macro_use]
 extern crate failure;
 
 use failure::Fail;
@@ -16,7 +15,7 @@ fn bad_function() -> Result<(), WrappingError> {
 }
 
 fn main() {
-    for cause in Fail::iter_chain(&bad_function().unwrap_err()) {
+    for cause in <dyn Fail>::iter_chain(&bad_function().unwrap_err()) {
         println!("{}: {}", cause.name().unwrap_or("Error"), cause);
     }
 }
@@ -7,6 +7,7 @@ fn main() {
     if rustc_has_dyn_trait() {
         println!("cargo:rustc-cfg=has_dyn_trait");
     }
+    println!("cargo:rustc-check-cfg=cfg(has_dyn_trait)");
 }
 
 fn rustc_has_dyn_trait() -> bool {
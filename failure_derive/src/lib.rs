@@ -33,45 +33,103 @@ impl From<syn::Error> for Error {
 
 decl_derive!([Fail, attributes(fail, cause)] => fail_derive);
 
-fn fail_derive(s: synstructure::Structure) -> TokenStream {
+fn fail_derive(mut s: synstructure::Structure) -> TokenStream {
+    s.underscore_const(true);
     match fail_derive_impl(s) {
         Err(err) => err.into_tokens(),
         Ok(tokens) => tokens,
     }
 }
 
-fn fail_derive_impl(s: synstructure::Structure) -> Result<TokenStream, Error> {
-    let make_dyn = if cfg!(has_dyn_trait) {
+fn make_dyn() -> TokenStream {
+    if cfg!(has_dyn_trait) {
         quote! { &dyn }
     } else {
         quote! { & }
-    };
+    }
+}
 
-    let ty_name = LitStr::new(&s.ast().ident.to_string(), Span::call_site());
+fn fail_derive_impl(s: synstructure::Structure) -> Result<TokenStream, Error> {
+    let (container, variant_attrs) = parse_item_attrs(&s)?;
+    if container.context_wrapper {
+        if container.display.is_some() {
+            return Err(Error::new(
+                s.ast().ident.span(),
+                "context_wrapper cannot be combined with a display attribute"
+            ));
+        }
+        return context_wrapper_derive(&s, &container);
+    }
+    check_fields(&s)?;
+
+    let make_dyn = make_dyn();
+
+    let name = match container.name {
+        Some(ref name) => quote!(#name),
+        None => {
+            let ty_name = LitStr::new(&s.ast().ident.to_string(), Span::call_site());
+            quote!(concat!(module_path!(), "::", #ty_name))
+        }
+    };
 
     let cause_body = s.each_variant(|v| {
         if let Some(cause) = v.bindings().iter().find(is_cause) {
-            quote!(return Some(::failure::AsFail::as_fail(#cause)))
+            let (span, cause) = spanned_binding(cause);
+            quote_spanned!(span => return Some(::failure::AsFail::as_fail(#cause)))
         } else {
             quote!(return None)
         }
     });
 
     let bt_body = s.each_variant(|v| {
-        if let Some(bi) = v.bindings().iter().find(is_backtrace) {
-            quote!(return Some(#bi))
-        } else {
-            quote!(return None)
+        let field = v.bindings().iter().filter_map(|bi| {
+            backtrace_field(bi.ast()).map(|bt| (bi, bt))
+        }).next();
+        match field {
+            Some((bi, BacktraceField::Plain)) => {
+                let (span, bi) = spanned_binding(bi);
+                quote_spanned!(span => return Some::<&::failure::Backtrace>(#bi))
+            }
+            Some((bi, BacktraceField::Optional)) => {
+                let (span, bi) = spanned_binding(bi);
+                quote_spanned!(span => return ::failure::_core::option::Option::as_ref(#bi))
+            }
+            None => quote!(return None),
+        }
+    });
+
+    let transient_fn = transient_fn(&s, &container, &variant_attrs);
+    let kind = container.kind;
+
+    let kind_fn = kind.as_ref().map(|kind| {
+        let kind_body = s.each_variant(|v| {
+            let ident = v.ast().ident;
+            quote!(return Some(&#kind::#ident))
+        });
+        quote! {
+            #[allow(unreachable_code)]
+            fn __private_get_kind__(&self) -> ::failure::_core::option::Option<#make_dyn(::failure::_core::any::Any)> {
+                match *self { #kind_body }
+                None
+            }
         }
     });
 
+    let code_fn = code_fn(&s, &variant_attrs)?;
+
     let fail = s.unbound_impl(
         quote!(::failure::Fail),
         quote! {
             fn name(&self) -> Option<&str> {
-                Some(concat!(module_path!(), "::", #ty_name))
+                Some(#name)
             }
 
+            #code_fn
+
+            #transient_fn
+
+            #kind_fn
+
             #[allow(unreachable_code)]
             fn cause(&self) -> ::failure::_core::option::Option<#make_dyn(::failure::Fail)> {
                 match *self { #cause_body }
@@ -85,7 +143,7 @@ fn fail_derive_impl(s: synstructure::Structure) -> Result<TokenStream, Error> {
             }
         },
     );
-    let display = display_body(&s)?.map(|display_body| {
+    let display = display_body(&s, &variant_attrs)?.map(|display_body| {
         s.unbound_impl(
             quote!(::failure::_core::fmt::Display),
             quote! {
@@ -98,71 +156,533 @@ fn fail_derive_impl(s: synstructure::Structure) -> Result<TokenStream, Error> {
         )
     });
 
+    let kind_items = kind.map(|kind| kind_items(&s, &kind));
+    let from_impls = from_impls(&s)?;
+    let explain = explain_impl(&s, &variant_attrs)?;
+
     Ok(quote! {
         #fail
         #display
+        #kind_items
+        #from_impls
+        #explain
+    })
+}
+
+fn code_fn(s: &synstructure::Structure, variant_attrs: &[FailAttrs]) -> Result<Option<TokenStream>, Error> {
+    let mut codes: Vec<&LitStr> = Vec::new();
+    for attrs in variant_attrs {
+        if let Some(ref code) = attrs.code {
+            if codes.iter().any(|other| other.value() == code.value()) {
+                return Err(Error::new(
+                    code.span(),
+                    &format!("Error code `{}` is used more than once", code.value())
+                ));
+            }
+            codes.push(code);
+        } else if let Some(ref explain) = attrs.explain {
+            return Err(Error::new(
+                explain.span(),
+                "`explain` requires an error code, e.g. `#[fail(code = \"E0001\")]`"
+            ));
+        }
+    }
+    if codes.is_empty() {
+        return Ok(None);
+    }
+
+    let arms = s.variants().iter().zip(variant_attrs).map(|(v, attrs)| {
+        let pat = v.pat();
+        match attrs.code {
+            Some(ref code) => quote!(#pat => return Some(#code),),
+            None => quote!(#pat => return None,),
+        }
+    });
+    Ok(Some(quote! {
+        #[allow(unreachable_code)]
+        fn code(&self) -> ::failure::_core::option::Option<&str> {
+            match *self { #(#arms)* }
+            None
+        }
+    }))
+}
+
+/// Implements `is_transient` from the `transient` and `permanent` attributes.
+/// For enums, an attribute on the enum applies to the variants without one.
+fn transient_fn(
+    s: &synstructure::Structure,
+    container: &FailAttrs,
+    variant_attrs: &[FailAttrs],
+) -> Option<TokenStream> {
+    if variant_attrs.iter().chain(Some(container)).all(|attrs| attrs.transient.is_none()) {
+        return None;
+    }
+    let arms = s.variants().iter().zip(variant_attrs).map(|(v, attrs)| {
+        let pat = v.pat();
+        match attrs.transient.or(container.transient) {
+            Some(transient) => quote!(#pat => return Some(#transient),),
+            None => quote!(#pat => return None,),
+        }
+    });
+    Some(quote! {
+        #[allow(unreachable_code)]
+        fn is_transient(&self) -> ::failure::_core::option::Option<bool> {
+            match *self { #(#arms)* }
+            None
+        }
     })
 }
 
-fn display_body(s: &synstructure::Structure) -> Result<Option<TokenStream>, Error> {
-    let mut msgs = s.variants().iter().map(|v| find_error_msg(&v.ast().attrs));
-    if msgs.all(|msg| msg.map(|m| m.is_none()).unwrap_or(true)) {
+/// Implements `Explain` with the long-form explanation of each error code,
+/// taken from the `explain` attribute or else the doc comment. A code without
+/// either is an error, rather than being registered with an empty explanation.
+fn explain_impl(s: &synstructure::Structure, variant_attrs: &[FailAttrs]) -> Result<Option<TokenStream>, Error> {
+    let mut entries = Vec::new();
+    for (v, attrs) in s.variants().iter().zip(variant_attrs) {
+        let code = match attrs.code {
+            Some(ref code) => code,
+            None => continue,
+        };
+        let text = match attrs.explain {
+            Some(ref explain) => explain.value(),
+            None => doc_text(v.ast().attrs),
+        };
+        if text.trim().is_empty() {
+            return Err(Error::new(
+                code.span(),
+                &format!(
+                    "Error code `{}` has no explanation, add a doc comment or `explain = \"...\"`",
+                    code.value()
+                )
+            ));
+        }
+        entries.push(quote!(::failure::Explanation { code: #code, text: #text }));
+    }
+    if entries.is_empty() {
         return Ok(None);
     }
 
+    Ok(Some(s.unbound_impl(
+        quote!(::failure::Explain),
+        quote! {
+            fn explanations() -> &'static [::failure::Explanation] {
+                const EXPLANATIONS: &[::failure::Explanation] = &[#(#entries),*];
+                EXPLANATIONS
+            }
+        },
+    )))
+}
+
+fn doc_text(attrs: &[syn::Attribute]) -> String {
+    let lines = attrs.iter().filter_map(|attr| match attr.parse_meta() {
+        Ok(syn::Meta::NameValue(syn::MetaNameValue { ref path, lit: syn::Lit::Str(ref lit), .. }))
+            if path.is_ident("doc") => Some(lit.value()),
+        _ => None,
+    }).collect::<Vec<_>>();
+    let lines = lines.iter().map(|line| line.strip_prefix(' ').unwrap_or(line));
+    lines.collect::<Vec<_>>().join("\n").trim().to_string()
+}
+
+/// Generates a `From` impl for each field marked `#[fail(from)]`, capturing
+/// a backtrace into the variant's backtrace field if it has one.
+fn from_impls(s: &synstructure::Structure) -> Result<TokenStream, Error> {
     let mut tokens = TokenStream::new();
     for v in s.variants() {
-        let msg =
-            find_error_msg(&v.ast().attrs)?
-              .ok_or_else(|| Error::new(
-                  v.ast().ident.span(),
-                  "All variants must have display attribute."
-              ))?;
-        if msg.nested.is_empty() {
+        let from = match v.bindings().iter().position(is_from) {
+            Some(from) => from,
+            None => continue,
+        };
+        for (i, bi) in v.bindings().iter().enumerate() {
+            if i != from && backtrace_field(bi.ast()).is_none() {
+                return Err(Error::new(
+                    field_span(bi.ast()),
+                    &format!(
+                        "`from` requires the other fields of `{}` to be backtraces, \
+                         as they cannot be filled in by the conversion",
+                        variant_name(s, v)
+                    )
+                ));
+            }
+        }
+
+        let from_ty = &v.bindings()[from].ast().ty;
+        let construct = v.construct(|field, i| {
+            if i == from {
+                return quote!(source);
+            }
+            match backtrace_field(field) {
+                Some(BacktraceField::Optional) => {
+                    quote!(::failure::_core::option::Option::Some(::failure::Backtrace::new()))
+                }
+                _ => quote!(::failure::Backtrace::new()),
+            }
+        });
+        tokens.extend(s.unbound_impl(
+            quote!(::failure::_core::convert::From<#from_ty>),
+            quote! {
+                fn from(source: #from_ty) -> Self {
+                    #construct
+                }
+            },
+        ));
+    }
+    Ok(tokens)
+}
+
+/// Parses the attributes of the type itself and of each of its variants. For
+/// structs, the attributes of the single variant are those of the struct.
+fn parse_item_attrs(s: &synstructure::Structure) -> Result<(FailAttrs, Vec<FailAttrs>), Error> {
+    let ast = s.ast();
+    match ast.data {
+        syn::Data::Enum(_) => {
+            let container = parse_attrs(&ast.attrs, Position::Enum)?;
+            let variants = s.variants()
+                .iter()
+                .map(|v| parse_attrs(v.ast().attrs, Position::Variant))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok((container, variants))
+        }
+        _ => {
+            let container = parse_attrs(&ast.attrs, Position::Struct)?;
+            Ok((container.clone(), vec![container]))
+        }
+    }
+}
+
+fn check_fields(s: &synstructure::Structure) -> Result<(), Error> {
+    for v in s.variants() {
+        let mut cause = false;
+        let mut backtrace = false;
+        for bi in v.bindings() {
+            let attrs = parse_attrs(&bi.ast().attrs, Position::Field)?;
+            if attrs.cause || attrs.from {
+                if cause {
+                    return Err(Error::new(
+                        field_span(bi.ast()),
+                        &format!("Cannot have two `cause` fields in `{}`", variant_name(s, v))
+                    ));
+                }
+                cause = true;
+            }
+            if backtrace_field(bi.ast()).is_some() {
+                if backtrace {
+                    return Err(Error::new(
+                        field_span(bi.ast()),
+                        &format!("Cannot have two `Backtrace` fields in `{}`", variant_name(s, v))
+                    ));
+                }
+                backtrace = true;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the binding for a field re-spanned to the field's type, so that
+/// type errors in code generated for it point at the field.
+fn spanned_binding(bi: &synstructure::BindingInfo) -> (Span, syn::Ident) {
+    let span = bi.ast().ty.span();
+    (span, syn::Ident::new(&bi.binding.to_string(), span))
+}
+
+fn field_span(field: &syn::Field) -> Span {
+    match field.ident {
+        Some(ref ident) => ident.span(),
+        None => field.ty.span(),
+    }
+}
+
+fn variant_name(s: &synstructure::Structure, v: &synstructure::VariantInfo) -> String {
+    match v.prefix {
+        Some(_) => format!("{}::{}", s.ast().ident, v.ast().ident),
+        None => s.ast().ident.to_string(),
+    }
+}
+
+fn context_wrapper_derive(s: &synstructure::Structure, container: &FailAttrs) -> Result<TokenStream, Error> {
+    let ast = s.ast();
+    let make_dyn = make_dyn();
+
+    let field = match ast.data {
+        syn::Data::Struct(ref data) if data.fields.iter().count() == 1 => {
+            data.fields.iter().next().unwrap()
+        }
+        _ => {
             return Err(Error::new(
-                msg.span(),
-                "Expected at least one argument to fail attribute"
+                ast.ident.span(),
+                "context_wrapper can only be derived for a struct with a single `Context` field"
             ));
         }
+    };
+    let member = match field.ident {
+        Some(ref ident) => syn::Member::Named(ident.clone()),
+        None => syn::Member::Unnamed(0.into()),
+    };
+    let context_ty = context_type(&field.ty).ok_or_else(|| Error::new(
+        field.ty.span(),
+        "context_wrapper field must have the type `Context<T>`"
+    ))?;
 
-        let format_string = match msg.nested[0] {
-            syn::NestedMeta::Meta(syn::Meta::NameValue(ref nv)) if nv.path.is_ident("display") => {
-                nv.lit.clone()
+    let ty = &ast.ident;
+    let vis = &ast.vis;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let name = match container.name {
+        Some(ref name) => quote!(#name),
+        None => {
+            let ty_name = LitStr::new(&ty.to_string(), Span::call_site());
+            quote!(concat!(module_path!(), "::", #ty_name))
+        }
+    };
+
+    let fail = s.unbound_impl(
+        quote!(::failure::Fail),
+        quote! {
+            fn name(&self) -> Option<&str> {
+                Some(#name)
+            }
+
+            fn code(&self) -> Option<&str> {
+                ::failure::Fail::code(&self.#member)
+            }
+
+            fn fields(&self) -> &[::failure::Field] {
+                ::failure::Fail::fields(&self.#member)
+            }
+
+            fn is_transient(&self) -> ::failure::_core::option::Option<bool> {
+                ::failure::Fail::is_transient(&self.#member)
+            }
+
+            fn cause(&self) -> ::failure::_core::option::Option<#make_dyn(::failure::Fail)> {
+                ::failure::Fail::cause(&self.#member)
+            }
+
+            fn backtrace(&self) -> ::failure::_core::option::Option<&::failure::Backtrace> {
+                ::failure::Fail::backtrace(&self.#member)
+            }
+
+            fn __private_get_kind__(&self) -> ::failure::_core::option::Option<#make_dyn(::failure::_core::any::Any)> {
+                ::failure::Fail::__private_get_kind__(&self.#member)
+            }
+        },
+    );
+    let display = s.unbound_impl(
+        quote!(::failure::_core::fmt::Display),
+        quote! {
+            fn fmt(&self, f: &mut ::failure::_core::fmt::Formatter) -> ::failure::_core::fmt::Result {
+                ::failure::_core::fmt::Display::fmt(&self.#member, f)
+            }
+        },
+    );
+    let from_context = s.unbound_impl(
+        quote!(::failure::_core::convert::From<::failure::Context<#context_ty>>),
+        quote! {
+            fn from(inner: ::failure::Context<#context_ty>) -> Self {
+                #ty { #member: inner }
+            }
+        },
+    );
+    let from_kind = s.unbound_impl(
+        quote!(::failure::_core::convert::From<#context_ty>),
+        quote! {
+            fn from(kind: #context_ty) -> Self {
+                #ty { #member: ::failure::Context::new(kind) }
+            }
+        },
+    );
+
+    let extra = if is_string(context_ty) {
+        let from_str = s.unbound_impl(
+            quote!(::failure::_core::convert::From<&'static str>),
+            quote! {
+                fn from(msg: &'static str) -> Self {
+                    #ty { #member: ::failure::Context::new(msg.into()) }
+                }
+            },
+        );
+        let from_str_context = s.unbound_impl(
+            quote!(::failure::_core::convert::From<::failure::Context<&'static str>>),
+            quote! {
+                fn from(inner: ::failure::Context<&'static str>) -> Self {
+                    #ty { #member: inner.map(|msg| msg.into()) }
+                }
+            },
+        );
+        quote! {
+            #from_str
+            #from_str_context
+        }
+    } else {
+        quote! {
+            impl #impl_generics #ty #ty_generics #where_clause {
+                /// Returns the kind of this error.
+                #vis fn kind(&self) -> #context_ty {
+                    ::failure::_core::clone::Clone::clone(self.#member.get_context())
+                }
             }
-            _ => {
+        }
+    };
+
+    Ok(quote! {
+        #fail
+        #display
+        #from_context
+        #from_kind
+        #extra
+    })
+}
+
+fn context_type(ty: &syn::Type) -> Option<&syn::Type> {
+    type_argument(ty, "Context")
+}
+
+/// Returns `T` if `ty` is a path to a type named `name<T>`.
+fn type_argument<'a>(ty: &'a syn::Type, name: &str) -> Option<&'a syn::Type> {
+    let segment = match *ty {
+        syn::Type::Path(syn::TypePath { qself: None, ref path }) => path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != name {
+        return None;
+    }
+    match segment.arguments {
+        syn::PathArguments::AngleBracketed(ref args) if args.args.len() == 1 => {
+            match args.args[0] {
+                syn::GenericArgument::Type(ref ty) => Some(ty),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn is_string(ty: &syn::Type) -> bool {
+    match *ty {
+        syn::Type::Path(syn::TypePath { qself: None, ref path }) => match path.segments.last() {
+            Some(s) => s.ident == "String" && s.arguments.is_empty(),
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+fn kind_items(s: &synstructure::Structure, kind: &syn::Ident) -> TokenStream {
+    let ast = s.ast();
+    let vis = &ast.vis;
+    let ty = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let variants = s.variants().iter().map(|v| v.ast().ident).collect::<Vec<_>>();
+    let variant_docs = variants.iter().map(|ident| {
+        LitStr::new(&format!("The kind of `{}::{}`.", ty, ident), Span::call_site())
+    });
+    let variant_names = variants.iter().map(|ident| {
+        LitStr::new(&ident.to_string(), Span::call_site())
+    });
+    let predicates = variants.iter().map(|ident| {
+        let method = syn::Ident::new(&format!("is_{}", snake_case(&ident.to_string())), ident.span());
+        let doc = LitStr::new(&format!("Returns true if this is a `{}::{}`.", ty, ident), Span::call_site());
+        quote! {
+            #[doc = #doc]
+            #vis fn #method(&self) -> bool {
+                self.kind() == #kind::#ident
+            }
+        }
+    });
+    let kind_body = s.each_variant(|v| {
+        let ident = v.ast().ident;
+        quote!(#kind::#ident)
+    });
+    let kind_doc = LitStr::new(&format!("The kinds of `{}`.", ty), Span::call_site());
+
+    quote! {
+        #[doc = #kind_doc]
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+        #vis enum #kind {
+            #(#[doc = #variant_docs] #variants,)*
+        }
+
+        impl ::failure::_core::fmt::Display for #kind {
+            fn fmt(&self, f: &mut ::failure::_core::fmt::Formatter) -> ::failure::_core::fmt::Result {
+                match *self {
+                    #(#kind::#variants => f.write_str(#variant_names),)*
+                }
+            }
+        }
+
+        impl #impl_generics #ty #ty_generics #where_clause {
+            /// Returns the kind of this error.
+            #vis fn kind(&self) -> #kind {
+                match *self { #kind_body }
+            }
+
+            #(#predicates)*
+        }
+    }
+}
+
+fn snake_case(ident: &str) -> String {
+    let chars = ident.chars().collect::<Vec<_>>();
+    let mut snake = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() {
+            let after_lower = i > 0 && (chars[i - 1].is_lowercase() || chars[i - 1].is_numeric());
+            let ends_acronym = i > 0 && chars[i - 1].is_uppercase()
+                && chars.get(i + 1).map(|c| c.is_lowercase()) == Some(true);
+            if after_lower || ends_acronym {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+fn display_body(
+    s: &synstructure::Structure,
+    variant_attrs: &[FailAttrs],
+) -> Result<Option<TokenStream>, Error> {
+    if variant_attrs.iter().all(|attrs| attrs.display.is_none()) {
+        return Ok(None);
+    }
+
+    let mut tokens = TokenStream::new();
+    for (v, attrs) in s.variants().iter().zip(variant_attrs) {
+        let msg = attrs.display.as_ref().ok_or_else(|| Error::new(
+            v.ast().ident.span(),
+            "All variants must have display attribute."
+        ))?;
+
+        let format_string = match msg.nested[0] {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                lit: syn::Lit::Str(ref lit), ..
+            })) => lit,
+            ref nested => {
                 return Err(Error::new(
-                    msg.span(),
-                    "Fail attribute must begin `display = \"\"` to control the Display message."
+                    nested.span(),
+                    "display must be a string literal, e.g. `display = \"An error occurred.\"`"
                 ));
             }
         };
         let args = msg.nested.iter().skip(1).map(|arg| match *arg {
             syn::NestedMeta::Lit(syn::Lit::Int(ref i)) => {
-                let bi = &v.bindings()[i.base10_parse::<usize>()?];
-                Ok(quote!(#bi))
+                let idx = i.base10_parse::<usize>()?;
+                match v.bindings().get(idx) {
+                    Some(bi) => Ok(quote!(#bi)),
+                    None => Err(missing_field(s, v, idx, arg.span())),
+                }
             }
             syn::NestedMeta::Meta(syn::Meta::Path(ref path)) => {
-                let id_s = path.get_ident().map(syn::Ident::to_string).unwrap_or("".to_string());
-                if id_s.starts_with("_") {
-                    if let Ok(idx) = id_s[1..].parse::<usize>() {
-                        let bi = match v.bindings().get(idx) {
-                            Some(bi) => bi,
-                            None => {
-                                return Err(Error::new(
-                                    arg.span(),
-                                    &format!(
-                                        "display attempted to access field `{}` in `{}::{}` which \
-                                     does not exist (there are {} field{})",
-                                        idx,
-                                        s.ast().ident,
-                                        v.ast().ident,
-                                        v.bindings().len(),
-                                        if v.bindings().len() != 1 { "s" } else { "" }
-                                    )
-                                ));
-                            }
+                let id_s = path.get_ident().map(syn::Ident::to_string).unwrap_or_default();
+                if let Some(idx) = id_s.strip_prefix('_') {
+                    if let Ok(idx) = idx.parse::<usize>() {
+                        return match v.bindings().get(idx) {
+                            Some(bi) => Ok(quote!(#bi)),
+                            None => Err(missing_field(s, v, idx, arg.span())),
                         };
-                        return Ok(quote!(#bi));
                     }
                 }
                 for bi in v.bindings() {
@@ -171,96 +691,413 @@ fn display_body(s: &synstructure::Structure) -> Result<Option<TokenStream>, Erro
                         return Ok(quote!(#bi));
                     }
                 }
-                return Err(Error::new(
+                Err(Error::new(
                     arg.span(),
                     &format!(
-                        "Couldn't find field `{:?}` in `{}::{}`",
-                        path,
-                        s.ast().ident,
-                        v.ast().ident
+                        "Couldn't find field `{}` in `{}`",
+                        quote!(#path),
+                        variant_name(s, v)
                     )
-                ));
+                ))
             }
             ref arg => {
-                return Err(Error::new(
+                Err(Error::new(
                     arg.span(),
                     "Invalid argument to fail attribute!"
-                ));
+                ))
             },
         });
         let args = args.collect::<Result<Vec<_>, _>>()?;
+        // Names in the format string refer to fields, or else to items in scope.
+        let named = check_format_args(format_string, args.len())?.into_iter().map(|name| {
+            let ident = syn::Ident::new(&name, format_string.span());
+            match v.bindings().iter().find(|bi| bi.ast().ident.as_ref() == Some(&ident)) {
+                Some(bi) => quote!(#ident = #bi),
+                None => quote!(#ident = #ident),
+            }
+        });
 
         let pat = v.pat();
-        tokens.extend(quote!(#pat => { return write!(f, #format_string #(, #args)*) }));
+        tokens.extend(quote!(#pat => { return write!(f, #format_string #(, #args)* #(, #named)*) }));
     }
     Ok(Some(tokens))
 }
 
-fn find_error_msg(attrs: &[syn::Attribute]) -> Result<Option<syn::MetaList>, Error> {
-    let mut error_msg = None;
+fn missing_field(s: &synstructure::Structure, v: &synstructure::VariantInfo, idx: usize, span: Span) -> Error {
+    Error::new(
+        span,
+        &format!(
+            "display attempted to access field `{}` in `{}` which \
+             does not exist (there are {} field{})",
+            idx,
+            variant_name(s, v),
+            v.bindings().len(),
+            if v.bindings().len() != 1 { "s" } else { "" }
+        )
+    )
+}
+
+/// Checks that the display format string uses exactly as many positional
+/// arguments as are passed to it, so that mistakes are reported against the
+/// attribute rather than inside the generated `write!`. Returns the names of
+/// the arguments the format string captures, such as `code` in `"{code}"`.
+fn check_format_args(format: &LitStr, args: usize) -> Result<Vec<String>, Error> {
+    let value = format.value();
+    let mut chars = value.chars().peekable();
+    let mut next_implicit = 0;
+    let mut required = 0;
+    let mut names = Vec::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+            }
+            '{' => {
+                let mut spec = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => spec.push(c),
+                        None => {
+                            return Err(Error::new(format.span(), "unterminated `{` in display format string"));
+                        }
+                    }
+                }
+                let (arg, fmt) = match spec.find(':') {
+                    Some(i) => (spec[..i].trim(), &spec[i + 1..]),
+                    None => (spec.trim(), ""),
+                };
+                // A `.*` precision takes its own argument before the value.
+                if fmt.contains(".*") {
+                    next_implicit += 1;
+                }
+                if arg.is_empty() {
+                    next_implicit += 1;
+                } else {
+                    format_arg(format, arg, &mut required, &mut names)?;
+                }
+                required = required.max(next_implicit);
+                for (i, _) in fmt.match_indices('$') {
+                    let start = fmt[..i]
+                        .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+                        .map_or(0, |start| start + 1);
+                    if start < i {
+                        format_arg(format, &fmt[start..i], &mut required, &mut names)?;
+                    }
+                }
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+            }
+            '}' => {
+                return Err(Error::new(format.span(), "unmatched `}` in display format string"));
+            }
+            _ => {}
+        }
+    }
+
+    if required != args {
+        return Err(Error::new(
+            format.span(),
+            &format!(
+                "display format string uses {} argument{} but {} {} given",
+                required,
+                if required != 1 { "s" } else { "" },
+                args,
+                if args != 1 { "were" } else { "was" }
+            )
+        ));
+    }
+    Ok(names)
+}
+
+/// Records an explicit argument of a display format string: either the index
+/// of a positional argument or the name of a captured one.
+fn format_arg(format: &LitStr, arg: &str, required: &mut usize, names: &mut Vec<String>) -> Result<(), Error> {
+    if let Ok(idx) = arg.parse::<usize>() {
+        *required = (*required).max(idx + 1);
+        return Ok(());
+    }
+    if syn::parse_str::<syn::Ident>(arg).is_err() {
+        return Err(Error::new(
+            format.span(),
+            &format!("invalid argument `{}` in display format string", arg)
+        ));
+    }
+    if !names.iter().any(|name| name == arg) {
+        names.push(arg.to_string());
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Position {
+    Struct,
+    Enum,
+    Variant,
+    Field,
+}
+
+impl Position {
+    fn describe(self) -> &'static str {
+        match self {
+            Position::Struct => "structs",
+            Position::Enum => "enums",
+            Position::Variant => "enum variants",
+            Position::Field => "fields",
+        }
+    }
+}
+
+const FAIL_KEYS: &str = "`display`, `name`, `code`, `explain`, `cause`, `backtrace`, `from`, \
+                         `kind`, `context_wrapper`, `transient`, `permanent`";
+
+/// The `#[fail(...)]` (and legacy `#[cause]`) attributes of one item.
+#[derive(Clone, Default)]
+struct FailAttrs {
+    display: Option<syn::MetaList>,
+    name: Option<LitStr>,
+    code: Option<LitStr>,
+    explain: Option<LitStr>,
+    kind: Option<syn::Ident>,
+    transient: Option<bool>,
+    context_wrapper: bool,
+    cause: bool,
+    backtrace: bool,
+    from: bool,
+}
+
+fn parse_attrs(attrs: &[syn::Attribute], position: Position) -> Result<FailAttrs, Error> {
+    let mut parsed = FailAttrs::default();
     for attr in attrs {
-        if let Ok(meta) = attr.parse_meta() {
-            if meta.path().is_ident("fail") {
-                if error_msg.is_some() {
+        if attr.path.is_ident("cause") {
+            allow_key("cause", attr.span(), position, &[Position::Field])?;
+            set_flag(&mut parsed.cause, "cause", attr.span())?;
+            continue;
+        }
+        if !attr.path.is_ident("fail") {
+            continue;
+        }
+
+        let list = match attr.parse_meta()? {
+            syn::Meta::List(list) => list,
+            meta => {
+                return Err(Error::new(
+                    meta.span(),
+                    "fail attribute must take a list in parentheses"
+                ));
+            }
+        };
+        if list.nested.is_empty() {
+            return Err(Error::new(
+                list.span(),
+                "Expected at least one argument to fail attribute"
+            ));
+        }
+
+        if let syn::NestedMeta::Meta(syn::Meta::NameValue(ref nv)) = list.nested[0] {
+            if nv.path.is_ident("display") {
+                allow_key("display", list.span(), position, &[Position::Struct, Position::Variant])?;
+                if parsed.display.is_some() {
                     return Err(Error::new(
-                        meta.span(),
+                        list.span(),
                         "Cannot have two display attributes"
                     ));
-                } else {
-                    if let syn::Meta::List(list) = meta {
-                        error_msg = Some(list);
-                    } else {
-                        return Err(Error::new(
-                            meta.span(),
-                            "fail attribute must take a list in parentheses"
-                        ));
-                    }
                 }
+                parsed.display = Some(list.clone());
+                continue;
             }
         }
+
+        for nested in &list.nested {
+            parse_key(nested, position, &mut parsed)?;
+        }
     }
-    Ok(error_msg)
+    Ok(parsed)
 }
 
-fn is_backtrace(bi: &&synstructure::BindingInfo) -> bool {
-    match bi.ast().ty {
+fn parse_key(nested: &syn::NestedMeta, position: Position, parsed: &mut FailAttrs) -> Result<(), Error> {
+    let meta = match *nested {
+        syn::NestedMeta::Meta(ref meta) => meta,
+        syn::NestedMeta::Lit(ref lit) => {
+            return Err(Error::new(
+                lit.span(),
+                &format!("Expected a key in fail attribute, one of {}", FAIL_KEYS)
+            ));
+        }
+    };
+    let key = meta.path().get_ident().map(syn::Ident::to_string).unwrap_or_default();
+    let span = meta.span();
+
+    match key.as_str() {
+        "display" => Err(Error::new(
+            span,
+            "`display = \"...\"` must be the first item in its fail attribute"
+        )),
+        "name" => {
+            let lit = str_meta(meta, &key, "my_crate.MyError")?;
+            allow_key(&key, span, position, &[Position::Struct, Position::Enum])?;
+            set_str(&mut parsed.name, lit, &key, span)
+        }
+        "code" => {
+            let lit = str_meta(meta, &key, "E0001")?;
+            allow_key(&key, span, position, &[Position::Struct, Position::Variant])?;
+            set_str(&mut parsed.code, lit, &key, span)
+        }
+        "explain" => {
+            let lit = str_meta(meta, &key, "A longer explanation of the error.")?;
+            allow_key(&key, span, position, &[Position::Struct, Position::Variant])?;
+            set_str(&mut parsed.explain, lit, &key, span)
+        }
+        "cause" => {
+            flag_meta(meta, &key)?;
+            allow_key(&key, span, position, &[Position::Field])?;
+            set_flag(&mut parsed.cause, &key, span)
+        }
+        "backtrace" => {
+            flag_meta(meta, &key)?;
+            allow_key(&key, span, position, &[Position::Field])?;
+            set_flag(&mut parsed.backtrace, &key, span)
+        }
+        "from" => {
+            flag_meta(meta, &key)?;
+            allow_key(&key, span, position, &[Position::Field])?;
+            set_flag(&mut parsed.from, &key, span)
+        }
+        "transient" | "permanent" => {
+            flag_meta(meta, &key)?;
+            allow_key(&key, span, position, &[Position::Struct, Position::Enum, Position::Variant])?;
+            if parsed.transient.is_some() {
+                return Err(Error::new(
+                    span,
+                    "Cannot have more than one of `transient` and `permanent`"
+                ));
+            }
+            parsed.transient = Some(key == "transient");
+            Ok(())
+        }
+        "context_wrapper" => {
+            flag_meta(meta, &key)?;
+            allow_key(&key, span, position, &[Position::Struct])?;
+            set_flag(&mut parsed.context_wrapper, &key, span)
+        }
+        "kind" => {
+            let lit = str_meta(meta, &key, "MyErrorKind")?;
+            allow_key(&key, span, position, &[Position::Enum])?;
+            if parsed.kind.is_some() {
+                return Err(Error::new(span, "Cannot have two kind attributes"));
+            }
+            parsed.kind = Some(lit.parse::<syn::Ident>()?);
+            Ok(())
+        }
+        _ => Err(Error::new(
+            span,
+            &format!(
+                "Unknown fail attribute `{}`, expected one of {}",
+                quote!(#meta),
+                FAIL_KEYS
+            )
+        )),
+    }
+}
+
+fn allow_key(key: &str, span: Span, position: Position, allowed: &[Position]) -> Result<(), Error> {
+    if allowed.contains(&position) {
+        return Ok(());
+    }
+    let allowed = allowed.iter().map(|p| p.describe()).collect::<Vec<_>>().join(" and ");
+    Err(Error::new(
+        span,
+        &format!(
+            "`{}` attribute is not allowed on {}, only on {}",
+            key,
+            position.describe(),
+            allowed
+        )
+    ))
+}
+
+fn set_flag(flag: &mut bool, key: &str, span: Span) -> Result<(), Error> {
+    if *flag {
+        return Err(Error::new(span, &format!("Cannot have two `{}` attributes", key)));
+    }
+    *flag = true;
+    Ok(())
+}
+
+fn set_str(value: &mut Option<LitStr>, lit: &LitStr, key: &str, span: Span) -> Result<(), Error> {
+    if value.is_some() {
+        return Err(Error::new(span, &format!("Cannot have two `{}` attributes", key)));
+    }
+    *value = Some(lit.clone());
+    Ok(())
+}
+
+fn flag_meta(meta: &syn::Meta, key: &str) -> Result<(), Error> {
+    match *meta {
+        syn::Meta::Path(_) => Ok(()),
+        _ => Err(Error::new(
+            meta.span(),
+            &format!("`{}` attribute does not take a value, write `#[fail({})]`", key, key)
+        )),
+    }
+}
+
+fn str_meta<'a>(meta: &'a syn::Meta, key: &str, example: &str) -> Result<&'a LitStr, Error> {
+    match *meta {
+        syn::Meta::NameValue(syn::MetaNameValue { lit: syn::Lit::Str(ref lit), .. }) => Ok(lit),
+        _ => Err(Error::new(
+            meta.span(),
+            &format!("`{}` attribute must be a string, e.g. `{} = \"{}\"`", key, key, example)
+        )),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum BacktraceField {
+    /// A `Backtrace` field.
+    Plain,
+    /// An `Option<Backtrace>` field.
+    Optional,
+}
+
+/// Determines whether a field holds the backtrace of a failure, either because
+/// it is marked `#[fail(backtrace)]` or because its type is named `Backtrace`
+/// or `Option<Backtrace>`.
+fn backtrace_field(field: &syn::Field) -> Option<BacktraceField> {
+    let explicit = parse_attrs(&field.attrs, Position::Field)
+        .map(|attrs| attrs.backtrace)
+        .unwrap_or(false);
+    match type_argument(&field.ty, "Option") {
+        Some(inner) if explicit || is_backtrace_type(inner) => Some(BacktraceField::Optional),
+        _ if explicit || is_backtrace_type(&field.ty) => Some(BacktraceField::Plain),
+        _ => None,
+    }
+}
+
+fn is_backtrace_type(ty: &syn::Type) -> bool {
+    match *ty {
         syn::Type::Path(syn::TypePath {
             qself: None,
             path: syn::Path {
                 segments: ref path, ..
             },
-        }) => path.last().map_or(false, |s| {
-            s.ident == "Backtrace" && s.arguments.is_empty()
-        }),
+        }) => match path.last() {
+            Some(s) => s.ident == "Backtrace" && s.arguments.is_empty(),
+            None => false,
+        },
         _ => false,
     }
 }
 
 fn is_cause(bi: &&synstructure::BindingInfo) -> bool {
-    let mut found_cause = false;
-    for attr in &bi.ast().attrs {
-        if let Ok(meta) = attr.parse_meta() {
-            if meta.path().is_ident("cause") {
-                if found_cause {
-                    panic!("Cannot have two `cause` attributes");
-                }
-                found_cause = true;
-            }
-            if meta.path().is_ident("fail") {
-                if let syn::Meta::List(ref list) = meta {
-                    if let Some(ref pair) = list.nested.first() {
-                        if let &&syn::NestedMeta::Meta(syn::Meta::Path(ref path)) = pair {
-                            if path.is_ident("cause") {
-                                if found_cause {
-                                    panic!("Cannot have two `cause` attributes");
-                                }
-                                found_cause = true;
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-    found_cause
+    parse_attrs(&bi.ast().attrs, Position::Field)
+        .map(|attrs| attrs.cause || attrs.from)
+        .unwrap_or(false)
+}
+
+fn is_from(bi: &synstructure::BindingInfo) -> bool {
+    parse_attrs(&bi.ast().attrs, Position::Field)
+        .map(|attrs| attrs.from)
+        .unwrap_or(false)
 }
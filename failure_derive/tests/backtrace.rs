@@ -1,5 +1,4 @@
 extern crate failure;
-#[macro_use]
 extern crate failure_derive;
 
 use failure::{Backtrace, Fail};
@@ -35,6 +34,7 @@ fn backtrace_tuple_error() {
 }
 
 #[derive(Fail, Debug)]
+#[allow(clippy::enum_variant_names)]
 enum BacktraceEnumError {
     #[fail(display = "Error code: {}", code)]
     StructVariant { code: i32, backtrace: Backtrace },
@@ -62,3 +62,36 @@ fn backtrace_enum_error() {
     assert_eq!(&s[..], "An error has occurred.");
     assert!(err.backtrace().is_none());
 }
+
+type Trace = Backtrace;
+
+#[derive(Fail, Debug)]
+#[fail(display = "An error has occurred.")]
+struct AliasBacktraceError {
+    #[fail(backtrace)]
+    trace: Trace,
+}
+
+#[test]
+fn explicit_backtrace_error() {
+    let err = AliasBacktraceError { trace: Backtrace::new() };
+    assert!(err.backtrace().is_some());
+}
+
+#[derive(Fail, Debug)]
+#[fail(display = "An error has occurred.")]
+struct OptionBacktraceError {
+    backtrace: Option<Backtrace>,
+}
+
+#[test]
+fn option_backtrace_error() {
+    let err = OptionBacktraceError { backtrace: Some(Backtrace::new()) };
+    assert!(err.backtrace().is_some());
+    let err = OptionBacktraceError { backtrace: None };
+    assert!(err.backtrace().is_none());
+
+    // Without a backtrace of its own, `Error` captures one on conversion.
+    let err = failure::Error::from(err);
+    assert!(err.as_fail().backtrace().is_none());
+}
@@ -0,0 +1,62 @@
+extern crate failure;
+extern crate failure_derive;
+
+use failure::{Error, Explain, Explanations, Fail, ResultExt};
+
+/// The configuration file could not be parsed.
+///
+/// Check the file for syntax errors.
+#[derive(Fail, Debug)]
+#[fail(display = "invalid configuration")]
+#[fail(code = "E1042")]
+struct ConfigError;
+
+#[derive(Fail, Debug)]
+enum NetworkError {
+    #[fail(display = "connection refused")]
+    #[fail(code = "E2001", explain = "The server refused the connection.")]
+    Refused,
+    /// The server did not answer in time.
+    #[fail(display = "timed out")]
+    #[fail(code = "E2002")]
+    TimedOut,
+    #[fail(display = "unknown")]
+    Unknown,
+}
+
+#[test]
+fn codes() {
+    assert_eq!(ConfigError.code(), Some("E1042"));
+    assert_eq!(NetworkError::Refused.code(), Some("E2001"));
+    assert_eq!(NetworkError::TimedOut.code(), Some("E2002"));
+    assert_eq!(NetworkError::Unknown.code(), None);
+}
+
+#[test]
+fn code_in_chain() {
+    let err: Error = Err::<(), _>(ConfigError)
+        .context("loading settings")
+        .unwrap_err()
+        .into();
+    assert_eq!(err.code(), Some("E1042"));
+
+    let err: Error = NetworkError::Unknown.into();
+    assert_eq!(err.code(), None);
+}
+
+#[test]
+fn explanations() {
+    let explanations = ConfigError::explanations();
+    assert_eq!(explanations.len(), 1);
+    assert_eq!(explanations[0].code, "E1042");
+    assert_eq!(
+        explanations[0].text,
+        "The configuration file could not be parsed.\n\nCheck the file for syntax errors."
+    );
+
+    let mut registry = Explanations::new();
+    registry.register::<ConfigError>().register::<NetworkError>();
+    assert_eq!(registry.get("E2001"), Some("The server refused the connection."));
+    assert_eq!(registry.get("E2002"), Some("The server did not answer in time."));
+    assert_eq!(registry.iter().count(), 3);
+}
@@ -0,0 +1,78 @@
+extern crate failure;
+extern crate failure_derive;
+
+use std::io;
+
+use failure::{Context, Fail, ResultExt};
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Fail)]
+enum ErrorKind {
+    #[fail(display = "could not read the config")]
+    Config,
+    #[fail(display = "network failure")]
+    Network,
+}
+
+#[derive(Debug, Fail)]
+#[fail(context_wrapper)]
+struct KindError {
+    inner: Context<ErrorKind>,
+}
+
+#[derive(Debug, Fail)]
+#[fail(context_wrapper)]
+struct MessageError(Context<String>);
+
+fn read_config() -> Result<(), KindError> {
+    Err(io::Error::from_raw_os_error(2)).context(ErrorKind::Config)?;
+    Ok(())
+}
+
+#[test]
+fn wrapper_delegates_to_context() {
+    let err = read_config().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Config);
+    assert_eq!(err.to_string(), "could not read the config");
+    assert!(err
+        .cause()
+        .and_then(|cause| cause.downcast_ref::<io::Error>())
+        .is_some());
+    assert!(err.backtrace().is_some());
+    assert_eq!(err.name(), Some("context_wrapper::KindError"));
+}
+
+#[test]
+fn wrapper_from_kind() {
+    let err = KindError::from(ErrorKind::Network);
+    assert_eq!(err.kind(), ErrorKind::Network);
+    assert!(err.cause().is_none());
+    assert_eq!(
+        failure::Error::from(err).find_kind::<ErrorKind>(),
+        Some(ErrorKind::Network)
+    );
+}
+
+fn nested() -> Result<(), MessageError> {
+    Err(MessageError::from("inner"))?;
+    Ok(())
+}
+
+#[test]
+fn wrapper_from_strings() {
+    let err = nested().context("outer").map_err(MessageError::from).unwrap_err();
+    assert_eq!(err.to_string(), "outer");
+    assert_eq!(err.cause().unwrap().to_string(), "inner");
+
+    let err = nested()
+        .context("formatted".to_string())
+        .map_err(MessageError::from)
+        .unwrap_err();
+    assert_eq!(err.to_string(), "formatted");
+}
+
+#[test]
+fn wrapper_forwards_fields() {
+    let err = KindError::from(Context::new(ErrorKind::Config).field("path", "/etc/app.toml"));
+    assert_eq!(err.fields().len(), 1);
+    assert_eq!(err.fields()[0].key(), "path");
+}
@@ -1,4 +1,3 @@
-#[macro_use]
 extern crate failure;
 
 use std::fmt::Debug;
@@ -0,0 +1,83 @@
+extern crate failure;
+extern crate failure_derive;
+
+use std::io;
+
+use failure::{Error, Fail, ResultExt};
+
+#[derive(Fail, Debug)]
+#[fail(kind = "StoreErrorKind")]
+pub enum StoreError {
+    #[fail(display = "key not found: {}", _0)]
+    NotFound(String),
+    #[fail(display = "IO error: {}", inner)]
+    IOFailure {
+        #[fail(cause)]
+        inner: io::Error,
+    },
+    #[fail(display = "store is read only")]
+    ReadOnly,
+}
+
+#[test]
+fn kind_matches_variant() {
+    assert_eq!(
+        StoreError::NotFound("a".to_string()).kind(),
+        StoreErrorKind::NotFound
+    );
+    let inner = io::Error::from_raw_os_error(98);
+    assert_eq!(StoreError::IOFailure { inner }.kind(), StoreErrorKind::IOFailure);
+    assert_eq!(StoreError::ReadOnly.kind(), StoreErrorKind::ReadOnly);
+}
+
+#[test]
+fn kind_predicates() {
+    let err = StoreError::NotFound("a".to_string());
+    assert!(err.is_not_found());
+    assert!(!err.is_io_failure());
+    assert!(!err.is_read_only());
+    assert!(StoreError::ReadOnly.is_read_only());
+}
+
+#[test]
+fn kind_display() {
+    assert_eq!(StoreErrorKind::NotFound.to_string(), "NotFound");
+    assert_eq!(StoreErrorKind::IOFailure.to_string(), "IOFailure");
+}
+
+#[test]
+fn find_kind_in_chain() {
+    let err: Error = Err::<(), _>(StoreError::ReadOnly)
+        .context("writing the index")
+        .unwrap_err()
+        .into();
+    assert_eq!(err.find_kind::<StoreErrorKind>(), Some(StoreErrorKind::ReadOnly));
+    assert_eq!(
+        err.as_fail().find_kind::<StoreErrorKind>(),
+        Some(StoreErrorKind::ReadOnly)
+    );
+
+    let err: Error = io::Error::from_raw_os_error(98).into();
+    assert_eq!(err.find_kind::<StoreErrorKind>(), None);
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Stage {
+    Parse,
+}
+
+impl std::fmt::Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[test]
+fn find_kind_from_context() {
+    let err: Error = Err::<(), _>(StoreError::ReadOnly)
+        .context(Stage::Parse)
+        .unwrap_err()
+        .into();
+    assert_eq!(err.find_kind::<Stage>(), Some(Stage::Parse));
+    assert_eq!(err.find_kind::<StoreErrorKind>(), Some(StoreErrorKind::ReadOnly));
+}
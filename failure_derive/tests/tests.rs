@@ -1,7 +1,8 @@
 extern crate failure;
-#[macro_use]
 extern crate failure_derive;
 
+use failure::Fail;
+
 #[derive(Fail, Debug)]
 #[fail(display = "An error has occurred.")]
 struct UnitError;
@@ -24,6 +25,22 @@ fn record_struct() {
     assert_eq!(&s[..], "Error code: 0");
 }
 
+const MAX_CODE: u32 = 99;
+
+#[derive(Fail, Debug)]
+#[fail(display = "Error code: {code:>width$} of {MAX_CODE}, from {}", f)]
+struct NamedArgsError {
+    code: u32,
+    width: usize,
+    f: &'static str,
+}
+
+#[test]
+fn named_args() {
+    let s = format!("{}", NamedArgsError { code: 7, width: 3, f: "input" });
+    assert_eq!(&s[..], "Error code:   7 of 99, from input");
+}
+
 #[derive(Fail, Debug)]
 #[fail(display = "Error code: {}", _0)]
 struct TupleError(i32);
@@ -35,6 +52,7 @@ fn tuple_struct() {
 }
 
 #[derive(Fail, Debug)]
+#[allow(clippy::enum_variant_names)]
 enum EnumError {
     #[fail(display = "Error code: {}", code)]
     StructVariant { code: i32 },
@@ -53,3 +71,22 @@ fn enum_error() {
     let s = format!("{}", EnumError::UnitVariant);
     assert_eq!(&s[..], "An error has occurred.");
 }
+
+#[derive(Fail, Debug)]
+#[fail(display = "An error has occurred.")]
+#[fail(name = "tests.named")]
+struct NamedError;
+
+#[derive(Fail, Debug)]
+#[fail(name = "tests.named_enum")]
+enum NamedEnumError {
+    #[fail(display = "An error has occurred.")]
+    UnitVariant,
+}
+
+#[test]
+fn custom_name() {
+    assert_eq!(NamedError.name(), Some("tests.named"));
+    assert_eq!(NamedEnumError::UnitVariant.name(), Some("tests.named_enum"));
+    assert_eq!(UnitError.name(), Some("tests::UnitError"));
+}
@@ -0,0 +1,58 @@
+extern crate failure;
+extern crate failure_derive;
+
+use failure::{Error, Fail, ResultExt};
+
+#[derive(Fail, Debug)]
+#[fail(display = "rate limited")]
+#[fail(transient)]
+struct RateLimited;
+
+#[derive(Fail, Debug)]
+#[fail(permanent)]
+enum RequestError {
+    #[fail(display = "timed out")]
+    #[fail(transient)]
+    TimedOut,
+    #[fail(display = "not found")]
+    NotFound,
+}
+
+#[derive(Fail, Debug)]
+enum ParseError {
+    #[fail(display = "unexpected end of input")]
+    #[fail(transient)]
+    Truncated,
+    #[fail(display = "invalid syntax")]
+    Syntax,
+}
+
+#[derive(Fail, Debug)]
+#[fail(display = "quota exceeded")]
+#[fail(permanent)]
+struct Quota(#[fail(cause)] RateLimited);
+
+#[test]
+fn classification() {
+    assert_eq!(RateLimited.is_transient(), Some(true));
+    assert_eq!(RequestError::TimedOut.is_transient(), Some(true));
+    assert_eq!(RequestError::NotFound.is_transient(), Some(false));
+    assert_eq!(ParseError::Truncated.is_transient(), Some(true));
+    assert_eq!(ParseError::Syntax.is_transient(), None);
+}
+
+#[test]
+fn classification_in_chain() {
+    let err: Error = Err::<(), _>(RateLimited)
+        .context("fetching index")
+        .unwrap_err()
+        .into();
+    assert!(err.is_transient());
+
+    // The outermost classified failure wins.
+    let err: Error = Quota(RateLimited).into();
+    assert!(!err.is_transient());
+
+    let err: Error = ParseError::Syntax.into();
+    assert!(!err.is_transient());
+}
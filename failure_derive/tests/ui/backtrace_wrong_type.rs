@@ -0,0 +1,20 @@
+extern crate failure;
+
+use failure::Fail;
+
+mod other {
+    #[derive(Debug)]
+    pub struct Backtrace;
+}
+
+#[derive(Fail, Debug)]
+#[fail(display = "wrong backtrace")]
+struct WrongBacktrace {
+    backtrace: other::Backtrace,
+}
+
+#[derive(Fail, Debug)]
+#[fail(display = "two backtraces")]
+struct TwoBacktraces(failure::Backtrace, failure::Backtrace);
+
+fn main() {}
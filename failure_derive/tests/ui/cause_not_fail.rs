@@ -0,0 +1,12 @@
+extern crate failure;
+
+use failure::Fail;
+
+#[derive(Fail, Debug)]
+#[fail(display = "bad cause")]
+struct BadCause {
+    #[fail(cause)]
+    code: u32,
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+extern crate failure;
+
+use failure::Fail;
+use std::{fmt, io};
+
+#[derive(Fail, Debug)]
+#[fail(display = "two causes")]
+struct TwoCauseFields {
+    #[fail(cause)]
+    io: io::Error,
+    #[fail(cause)]
+    fmt: fmt::Error,
+}
+
+#[derive(Fail, Debug)]
+#[fail(display = "cause twice")]
+struct CauseTwice(#[cause] #[fail(cause)] io::Error);
+
+fn main() {}
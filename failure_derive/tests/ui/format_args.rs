@@ -0,0 +1,29 @@
+extern crate failure;
+
+use failure::Fail;
+
+#[derive(Fail, Debug)]
+#[fail(display = "{} and {}", code)]
+struct TooFew {
+    code: u32,
+}
+
+#[derive(Fail, Debug)]
+#[fail(display = "just {}", _0, _1)]
+struct TooMany(u32, u32);
+
+#[derive(Fail, Debug)]
+#[fail(display = "invalid {code-1}")]
+struct InvalidName {
+    code: u32,
+}
+
+#[derive(Fail, Debug)]
+#[fail(display = "out of range {1}", _0)]
+struct OutOfRange(u32);
+
+#[derive(Fail, Debug)]
+#[fail(display = "missing {}", _3)]
+struct MissingField(u32);
+
+fn main() {}
@@ -0,0 +1,13 @@
+extern crate failure;
+
+use failure::Fail;
+
+#[derive(Fail, Debug)]
+#[fail(display = "config error in {}", path)]
+struct ConfigError {
+    #[fail(from)]
+    source: std::io::Error,
+    path: String,
+}
+
+fn main() {}
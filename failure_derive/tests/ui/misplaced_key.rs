@@ -0,0 +1,21 @@
+extern crate failure;
+
+use failure::Fail;
+
+#[derive(Fail, Debug)]
+#[fail(display = "cause on a struct")]
+#[fail(cause)]
+struct CauseOnStruct;
+
+#[derive(Fail, Debug)]
+#[fail(display = "display on an enum")]
+enum DisplayOnEnum {
+    #[fail(display = "a")]
+    A,
+}
+
+#[derive(Fail, Debug)]
+#[fail(kind = "Kind")]
+struct KindOnStruct;
+
+fn main() {}
@@ -0,0 +1,16 @@
+extern crate failure;
+
+use failure::Fail;
+
+#[derive(Fail, Debug)]
+enum ConfigError {
+    /// The configuration file could not be parsed.
+    #[fail(display = "invalid configuration")]
+    #[fail(code = "E1042")]
+    Invalid,
+    #[fail(display = "missing configuration")]
+    #[fail(code = "E1043")]
+    Missing,
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+extern crate failure;
+
+use failure::Fail;
+
+#[derive(Fail, Debug)]
+#[fail(dispaly = "typo")]
+struct Typo;
+
+#[derive(Fail, Debug)]
+enum Unknown {
+    #[fail(display = "a")]
+    A(#[fail(cause, because)] std::io::Error),
+}
+
+fn main() {}
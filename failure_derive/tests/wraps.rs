@@ -1,5 +1,4 @@
 extern crate failure;
-#[macro_use]
 extern crate failure_derive;
 
 use std::fmt;
@@ -83,7 +82,7 @@ fn wrap_enum_error() {
         .and_then(|err| err.downcast_ref::<io::Error>())
         .is_some());
     assert!(err.backtrace().is_none());
-    let fmt_error = fmt::Error::default();
+    let fmt_error = fmt::Error;
     let err: WrapEnumError = WrapEnumError::Fmt {
         inner: fmt_error,
         backtrace: Backtrace::new(),
@@ -96,3 +95,37 @@ fn wrap_enum_error() {
     assert!(err.backtrace().unwrap().is_empty());
     assert!(err.backtrace().unwrap().to_string().trim().is_empty());
 }
+
+#[derive(Fail, Debug)]
+enum FromError {
+    #[fail(display = "An IO error has occurred: {}", _0)]
+    Io(#[fail(from)] io::Error, Backtrace),
+    #[fail(display = "A format error has occurred: {}", source)]
+    Fmt {
+        #[fail(from)]
+        source: fmt::Error,
+        backtrace: Option<Backtrace>,
+    },
+}
+
+#[test]
+fn from_error() {
+    let err = FromError::from(io::Error::from_raw_os_error(98));
+    assert!(err
+        .cause()
+        .and_then(|err| err.downcast_ref::<io::Error>())
+        .is_some());
+    assert!(err.backtrace().is_some());
+
+    let err = FromError::from(fmt::Error);
+    assert!(err
+        .cause()
+        .and_then(|err| err.downcast_ref::<fmt::Error>())
+        .is_some());
+    assert!(err.backtrace().is_some());
+
+    // The backtrace captured by `From` is reused by `Error`.
+    let err = failure::Error::from(err);
+    let captured = err.as_fail().backtrace().unwrap();
+    assert!(std::ptr::eq(err.backtrace(), captured));
+}
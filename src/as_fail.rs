@@ -1,4 +1,6 @@
 use Fail;
+#[cfg(feature = "alloc")]
+use Error;
 
 /// The `AsFail` trait
 ///
@@ -9,6 +11,13 @@ use Fail;
 pub trait AsFail {
     /// Converts a reference to `Self` into a dynamic trait object of `Fail`.
     fn as_fail(&self) -> &dyn Fail;
+
+    /// Returns the errors suppressed by `Self`, which only an `Error` has.
+    #[doc(hidden)]
+    #[cfg(feature = "alloc")]
+    fn __private_suppressed__(&self) -> &[Error] {
+        &[]
+    }
 }
 
 impl<T> AsFail for T
@@ -26,12 +35,14 @@ impl AsFail for dyn Fail {
     }
 }
 
-with_std! {
-    use error::Error;
-
+with_alloc! {
     impl AsFail for Error {
         fn as_fail(&self) -> &dyn Fail {
             self.as_fail()
         }
+
+        fn __private_suppressed__(&self) -> &[Error] {
+            self.suppressed()
+        }
     }
 }
@@ -0,0 +1,115 @@
+use core::fmt::{self, Display};
+use alloc::string::String;
+
+use Fail;
+
+/// A failed check of `ensure_eq!`, `ensure_ne!`, `ensure_matches!` or an
+/// `ensure!` of a comparison.
+///
+/// This carries the text of the checked expression, the `Debug`
+/// representation of its operands and the location of the check. It can be
+/// downcast from an `Error` to inspect a failed check:
+///
+/// ```rust
+/// #[macro_use] extern crate failure;
+///
+/// use failure::{AssertionFailure, Error};
+///
+/// fn check_len(len: usize) -> Result<(), Error> {
+///     ensure_eq!(len, 4);
+///     Ok(())
+/// }
+///
+/// fn main() {
+///     let err = check_len(3).unwrap_err();
+///     let failure = err.downcast_ref::<AssertionFailure>().unwrap();
+///     assert_eq!(failure.expression(), "len == 4");
+///     assert_eq!(failure.left(), Some("3"));
+///     assert_eq!(failure.right(), Some("4"));
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssertionFailure {
+    expression: &'static str,
+    left: Option<String>,
+    right: Option<String>,
+    message: Option<String>,
+    file: &'static str,
+    line: u32,
+    column: u32,
+}
+
+impl AssertionFailure {
+    #[doc(hidden)]
+    pub fn _new(
+        expression: &'static str,
+        left: Option<String>,
+        right: Option<String>,
+        message: Option<String>,
+        (file, line, column): (&'static str, u32, u32),
+    ) -> AssertionFailure {
+        AssertionFailure { expression, left, right, message, file, line, column }
+    }
+
+    /// Returns the text of the checked expression, such as `a == b`.
+    pub fn expression(&self) -> &str {
+        self.expression
+    }
+
+    /// Returns the `Debug` representation of the left operand, or of the
+    /// value checked by `ensure_matches!`.
+    ///
+    /// This is `None` if the operand does not implement `Debug`.
+    pub fn left(&self) -> Option<&str> {
+        self.left.as_ref().map(|left| &left[..])
+    }
+
+    /// Returns the `Debug` representation of the right operand.
+    ///
+    /// This is `None` if the operand does not implement `Debug`, or for
+    /// `ensure_matches!`.
+    pub fn right(&self) -> Option<&str> {
+        self.right.as_ref().map(|right| &right[..])
+    }
+
+    /// Returns the custom message given to the check, if any.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_ref().map(|message| &message[..])
+    }
+
+    /// Returns the file containing the check.
+    pub fn file(&self) -> &str {
+        self.file
+    }
+
+    /// Returns the line of the check.
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Returns the column of the check.
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+}
+
+impl Fail for AssertionFailure {
+    fn name(&self) -> Option<&str> {
+        Some("failure::AssertionFailure")
+    }
+}
+
+impl Display for AssertionFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.message {
+            Some(ref message) => write!(f, "{}", message)?,
+            None => write!(f, "assertion failed: `{}`", self.expression)?,
+        }
+        match (self.left(), self.right()) {
+            (Some(left), Some(right)) => write!(f, " (left: `{}`, right: `{}`)", left, right),
+            (Some(left), None) => write!(f, " (left: `{}`)", left),
+            (None, Some(right)) => write!(f, " (right: `{}`)", right),
+            (None, None) => Ok(()),
+        }
+    }
+}
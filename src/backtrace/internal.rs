@@ -85,8 +85,8 @@ fn is_backtrace_enabled<F: Fn(&str) -> Option<OsString>>(get_var: F) -> bool {
         Some(ref val) if val != "0" => true,
         Some(ref val) if val == "0" => false,
         _ => match get_var(GENERAL_BACKTRACE) {
-            Some(ref val) if val != "0" => true,
-            _                           => false,
+            Some(ref val) => val != "0",
+            None          => false,
         }
     }
 }
@@ -0,0 +1,57 @@
+//! Prints the long-form explanation of an error code.
+//!
+//! Explanations are read from a directory of `<code>.md` files, given by
+//! `--dir` or the `FAILURE_EXPLAIN_DIR` environment variable. This binary has
+//! no explanations of its own: an application exports the directory with
+//! `failure::Explanations::write_dir`, for example from a test or a build
+//! step. A missing directory, an empty file, or a code without a file is
+//! reported as an error.
+extern crate failure;
+
+use std::env;
+use std::path::PathBuf;
+use std::process;
+
+use failure::Explanations;
+
+const USAGE: &str = "usage: failure-explain [--dir DIR] CODE";
+
+fn main() {
+    let mut dir = env::var_os("FAILURE_EXPLAIN_DIR").map(PathBuf::from);
+    let mut code = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            "--dir" => match args.next() {
+                Some(path) => dir = Some(PathBuf::from(path)),
+                None => exit_with(USAGE, 2),
+            },
+            _ if code.is_none() => code = Some(arg),
+            _ => exit_with(USAGE, 2),
+        }
+    }
+
+    let code = code.unwrap_or_else(|| exit_with(USAGE, 2));
+    let dir = dir.unwrap_or_else(|| {
+        exit_with("failure-explain: no explanations, pass --dir or set FAILURE_EXPLAIN_DIR", 2)
+    });
+
+    let mut explanations = Explanations::new();
+    if let Err(err) = explanations.load_dir(&dir) {
+        exit_with(&format!("failure-explain: cannot read {}: {}", dir.display(), err), 2);
+    }
+    match explanations.get(&code) {
+        Some(text) => println!("{}", text.trim_end()),
+        None => exit_with(&format!("failure-explain: no explanation for error code `{}`", code), 1),
+    }
+}
+
+fn exit_with(message: &str, code: i32) -> ! {
+    eprintln!("{}", message);
+    process::exit(code)
+}
@@ -16,4 +16,9 @@ impl fmt::Debug for BoxStd {
     }
 }
 
-impl Fail for BoxStd {}
+impl Fail for BoxStd {
+    fn name(&self) -> Option<&str> {
+        // The concrete type of the boxed error is no longer known.
+        Some(::std::any::type_name::<Box<dyn Error + Send + Sync + 'static>>())
+    }
+}
@@ -0,0 +1,173 @@
+use core::cell::{Cell, RefCell};
+use core::marker::PhantomData;
+
+use Error;
+
+thread_local! {
+    // The failures of the cleanups run by guards dropped without `finish`,
+    // kept while `collect_dropped` is active on this thread.
+    static DROPPED: RefCell<Vec<Error>> = RefCell::new(Vec::new());
+    // The number of `collect_dropped` calls active on this thread.
+    static COLLECTING: Cell<usize> = Cell::new(0);
+}
+
+/// Calls a closure, returning with its result the failures of the cleanups
+/// run by guards dropped without `finish` during the call.
+///
+/// If the closure panics, the failures are left to the enclosing call.
+pub(crate) fn collect_dropped<R, F: FnOnce() -> R>(f: F) -> (R, Vec<Error>) {
+    struct Collecting;
+
+    impl Drop for Collecting {
+        fn drop(&mut self) {
+            let collecting = COLLECTING.with(|collecting| {
+                collecting.set(collecting.get() - 1);
+                collecting.get()
+            });
+            if collecting == 0 {
+                DROPPED.with(|dropped| dropped.borrow_mut().clear());
+            }
+        }
+    }
+
+    let start = DROPPED.with(|dropped| dropped.borrow().len());
+    COLLECTING.with(|collecting| collecting.set(collecting.get() + 1));
+    let collecting = Collecting;
+    let result = f();
+    let dropped = DROPPED.with(|dropped| dropped.borrow_mut().split_off(start));
+    drop(collecting);
+    (result, dropped)
+}
+
+/// Records the failure of a cleanup run by a guard dropped without `finish`,
+/// for the enclosing `collect_dropped` call, if there is one.
+pub(crate) fn record_dropped(error: Error) {
+    if COLLECTING.try_with(Cell::get).unwrap_or(0) > 0 {
+        let _ = DROPPED.try_with(|dropped| dropped.borrow_mut().push(error));
+    }
+}
+
+/// A scope guard running fallible cleanup, such as a rollback, when the work
+/// it guards is finished.
+///
+/// Pass the result of the work to `finish`, which runs the cleanup. If both
+/// the work and the cleanup fail, the failure of the cleanup is attached to
+/// the error of the work as a suppressed error, instead of one of them being
+/// thrown away:
+///
+/// ```
+/// use failure::{err_msg, CleanupGuard, Error};
+///
+/// fn transfer() -> Result<(), Error> {
+///     let guard = CleanupGuard::new(|| Err(err_msg("rollback failed")));
+///     let result: Result<(), Error> = Err(err_msg("transfer failed"));
+///     guard.finish(result)
+/// }
+///
+/// let err = transfer().unwrap_err();
+/// assert_eq!(err.to_string(), "transfer failed");
+/// assert_eq!(err.suppressed()[0].to_string(), "rollback failed");
+/// ```
+///
+/// If the guard is dropped without calling `finish`, for example when
+/// returning early with `?` or unwinding from a panic, the cleanup still
+/// runs. Its failure is then attached to the error returned by the enclosing
+/// `CleanupGuard::run` or `catch_panic`, and is only discarded when there is
+/// none. Use `run`, which guards a closure, so that an early return from the
+/// work keeps the failure:
+///
+/// ```
+/// use failure::{err_msg, CleanupGuard, Error};
+///
+/// fn transfer() -> Result<(), Error> {
+///     Err(err_msg("transfer failed"))
+/// }
+///
+/// let err = CleanupGuard::new(|| Err(err_msg("rollback failed")))
+///     .run(|| -> Result<(), Error> {
+///         let _lock = CleanupGuard::new(|| Err(err_msg("unlock failed")));
+///         transfer()?;
+///         Ok(())
+///     })
+///     .unwrap_err();
+/// assert_eq!(err.to_string(), "transfer failed");
+/// assert_eq!(err.suppressed()[0].to_string(), "rollback failed");
+/// assert_eq!(err.suppressed()[1].to_string(), "unlock failed");
+/// ```
+pub struct CleanupGuard<F, E>
+where
+    F: FnOnce() -> Result<(), E>,
+    E: Into<Error>,
+{
+    cleanup: Option<F>,
+    _error: PhantomData<fn() -> E>,
+}
+
+impl<F, E> CleanupGuard<F, E>
+where
+    F: FnOnce() -> Result<(), E>,
+    E: Into<Error>,
+{
+    /// Creates a guard which will run `cleanup`.
+    pub fn new(cleanup: F) -> CleanupGuard<F, E> {
+        CleanupGuard { cleanup: Some(cleanup), _error: PhantomData }
+    }
+
+    /// Runs the cleanup, and combines its result with the result of the
+    /// guarded work.
+    ///
+    /// If only one of them failed, its error is returned. If both failed, the
+    /// error of the work is returned, with the error of the cleanup attached
+    /// as suppressed.
+    pub fn finish<T, P: Into<Error>>(mut self, result: Result<T, P>) -> Result<T, Error> {
+        let cleanup = match self.cleanup.take() {
+            Some(cleanup) => cleanup(),
+            None => Ok(()),
+        };
+        match (result, cleanup) {
+            (Ok(value), Ok(())) => Ok(value),
+            (Ok(_), Err(error)) => Err(error.into()),
+            (Err(error), Ok(())) => Err(error.into()),
+            (Err(error), Err(suppressed)) => Err(error.into().with_suppressed(suppressed)),
+        }
+    }
+
+    /// Runs the guarded work, then the cleanup, combining their results as
+    /// `finish` does.
+    ///
+    /// The failures of the cleanups of other guards dropped without `finish`
+    /// during the work, as when it returns early with `?`, are attached to
+    /// the returned error as suppressed, after the failure of this cleanup.
+    /// If only those cleanups failed, the first failure is returned.
+    pub fn run<T, P, W>(self, work: W) -> Result<T, Error>
+    where
+        P: Into<Error>,
+        W: FnOnce() -> Result<T, P>,
+    {
+        let (result, dropped) = collect_dropped(work);
+        let result = self.finish(result);
+        dropped.into_iter().fold(result, |result, error| match result {
+            Ok(_) => Err(error),
+            Err(primary) => Err(primary.with_suppressed(error)),
+        })
+    }
+
+    /// Drops the guard without running the cleanup.
+    pub fn cancel(mut self) {
+        self.cleanup = None;
+    }
+}
+
+impl<F, E> Drop for CleanupGuard<F, E>
+where
+    F: FnOnce() -> Result<(), E>,
+    E: Into<Error>,
+{
+    fn drop(&mut self) {
+        if let Some(cleanup) = self.cleanup.take() {
+            if let Err(error) = cleanup() {
+                record_dropped(error.into());
+            }
+        }
+    }
+}
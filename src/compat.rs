@@ -32,6 +32,7 @@ with_std! {
     use std::error::Error as StdError;
 
     use Error;
+    use box_std::BoxStd;
 
     impl<E: Display + Debug> StdError for Compat<E> {
         fn description(&self) -> &'static str {
@@ -39,15 +40,23 @@ with_std! {
         }
     }
 
+    // An `Error` made by `Error::from_boxed_compat` gives back the box it
+    // was made from.
     impl From<Error> for Box<dyn StdError> {
         fn from(error: Error) -> Box<dyn StdError> {
-            Box::new(Compat { error })
+            match error.downcast::<BoxStd>() {
+                Ok(BoxStd(error)) => error,
+                Err(error) => Box::new(Compat { error }),
+            }
         }
     }
 
     impl From<Error> for Box<dyn StdError + Send + Sync> {
         fn from(error: Error) -> Box<dyn StdError + Send + Sync> {
-            Box::new(Compat { error })
+            match error.downcast::<BoxStd>() {
+                Ok(BoxStd(error)) => error,
+                Err(error) => Box::new(Compat { error }),
+            }
         }
     }
 }
@@ -1,8 +1,9 @@
+use core::any::Any;
 use core::fmt::{self, Debug, Display};
 
 use Fail;
 
-without_std! {
+without_alloc! {
     /// An error with context around it.
     ///
     /// The context is intended to be a human-readable, user-facing explanation for the
@@ -11,6 +12,14 @@ without_std! {
     ///
     /// The `Display` impl for `Context` only prints the human-readable context, while the
     /// `Debug` impl also prints the underlying error.
+    ///
+    /// Without an allocator there is no `Error` to hold the underlying failure, so it is
+    /// dropped, and a `Context` has no cause. Keeping the failure would mean storing it
+    /// inline, making the type of the context depend on the type of the failure, while
+    /// with an allocator `Fail::context` returns a `Context<D>` whatever the failure.
+    /// Code which converts `Context<D>` into its own error types relies on that, and a
+    /// signature that changed with the `alloc` feature would make the feature break
+    /// crates which name it.
     pub struct Context<D: Display + Send + Sync + 'static> {
         context: D,
     }
@@ -41,7 +50,15 @@ without_std! {
         }
     }
 
-    impl<D: Display + Send + Sync + 'static> Fail for Context<D> { }
+    impl<D: Display + Send + Sync + 'static> Fail for Context<D> {
+        fn name(&self) -> Option<&str> {
+            Some("failure::Context")
+        }
+
+        fn __private_get_kind__(&self) -> Option<&dyn Any> {
+            Some(&self.context)
+        }
+    }
 
     impl<D: Display + Send + Sync + 'static> Debug for Context<D> {
         fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -57,13 +74,18 @@ without_std! {
 
     #[test]
     fn test_map() {
-        let ctx = Context::new("a string").map(|s| format!("{} with some more stuff", s));
-        assert_eq!(ctx.context, String::from("a string with some more stuff"));
+        let ctx = Context::new("a string").map(|s| s.len());
+        assert_eq!(ctx.context, 8);
     }
 }
 
-with_std! {
-    use {Error, Backtrace};
+with_alloc! {
+    use core::any::TypeId;
+    use alloc::boxed::Box;
+    use alloc::vec::Vec;
+
+    use {Backtrace, Error, Field, Value};
+    use error::{Link, Stacked};
 
     /// An error with context around it.
     ///
@@ -72,9 +94,10 @@ with_std! {
     /// information.
     ///
     /// The `Display` impl for `Context` only prints the human-readable context, while the
-    /// `Debug` impl also prints the underlying error.
+    /// `Debug` impl also prints the underlying error and the fields of the context.
     pub struct Context<D: Display + Send + Sync + 'static> {
         context: D,
+        fields: Vec<Field>,
         failure: Either<Backtrace, Error>,
     }
 
@@ -82,7 +105,22 @@ with_std! {
         /// Creates a new context without an underlying error message.
         pub fn new(context: D) -> Context<D> {
             let failure = Either::This(Backtrace::new());
-            Context { context, failure }
+            Context { context, fields: Vec::new(), failure }
+        }
+
+        /// Attaches a structured key-value field to this context.
+        ///
+        /// ```
+        /// use failure::{Context, Fail};
+        ///
+        /// let err = Context::new("reading config")
+        ///     .field("path", "/etc/app.toml")
+        ///     .field("attempt", 3);
+        /// assert_eq!(err.fields().len(), 2);
+        /// ```
+        pub fn field<V: Into<Value>>(mut self, key: &'static str, value: V) -> Context<D> {
+            self.fields.push(Field::new(key, value));
+            self
         }
 
         /// Returns a reference to the context provided with this error.
@@ -97,19 +135,36 @@ with_std! {
         {
             Context {
                 context: op(self.context),
+                fields: self.fields,
                 failure: self.failure,
             }
         }
 
         pub(crate) fn with_err<E: Into<Error>>(context: D, error: E) -> Context<D> {
             let failure = Either::That(error.into());
-            Context { context, failure }
+            Context { context, fields: Vec::new(), failure }
+        }
+
+        // Writes the context and its fields, after the underlying error.
+        fn fmt_context(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.context)?;
+            for (i, field) in self.fields.iter().enumerate() {
+                write!(f, "{}{}", if i == 0 { " (" } else { ", " }, field)?;
+            }
+            if !self.fields.is_empty() {
+                write!(f, ")")?;
+            }
+            Ok(())
         }
     }
 
     impl<D: Display + Send + Sync + 'static> Fail for Context<D> {
         fn name(&self) -> Option<&str> {
-            self.failure.as_cause().and_then(|x| x.name())
+            Some("failure::Context")
+        }
+
+        fn fields(&self) -> &[Field] {
+            &self.fields
         }
 
         fn cause(&self) -> Option<&dyn Fail> {
@@ -119,11 +174,16 @@ with_std! {
         fn backtrace(&self) -> Option<&Backtrace> {
             Some(self.failure.backtrace())
         }
+
+        fn __private_get_kind__(&self) -> Option<&dyn Any> {
+            Some(&self.context)
+        }
     }
 
     impl<D: Display + Send + Sync + 'static> Debug for Context<D> {
         fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-            write!(f, "{:?}\n\n{}", self.failure, self.context)
+            write!(f, "{:?}\n\n", self.failure)?;
+            self.fmt_context(f)
         }
     }
 
@@ -133,6 +193,82 @@ with_std! {
         }
     }
 
+    /// A `Context` pushed onto the stack of an `Error` by `Error::wrap`.
+    ///
+    /// The context itself has no cause, and the link to the rest of the error
+    /// is kept next to it rather than inside it. A stacked context downcasts
+    /// to the `Context` it holds, so that moving that `Context` out of the
+    /// error through `downcast_mut` cannot take the link along.
+    #[repr(C)]
+    pub(crate) struct StackedContext<D: Display + Send + Sync + 'static> {
+        // First, so that a pointer to the stacked context points to it.
+        context: Context<D>,
+        link: Link,
+    }
+
+    impl<D: Display + Send + Sync + 'static> StackedContext<D> {
+        pub(crate) fn new(context: D, link: Link) -> StackedContext<D> {
+            let failure = Either::This(Backtrace::none());
+            let context = Context { context, fields: Vec::new(), failure };
+            StackedContext { context, link }
+        }
+    }
+
+    impl<D: Display + Send + Sync + 'static> Stacked for StackedContext<D> {
+        fn as_fail(&self) -> &dyn Fail {
+            self
+        }
+
+        fn as_fail_mut(&mut self) -> &mut dyn Fail {
+            self
+        }
+
+        fn detach(self: Box<Self>, rest: Error) -> Box<dyn Any> {
+            let mut context = self.context;
+            context.failure = Either::That(rest);
+            Box::new(context)
+        }
+    }
+
+    impl<D: Display + Send + Sync + 'static> Fail for StackedContext<D> {
+        fn name(&self) -> Option<&str> {
+            self.context.name()
+        }
+
+        fn fields(&self) -> &[Field] {
+            self.context.fields()
+        }
+
+        fn cause(&self) -> Option<&dyn Fail> {
+            Some(self.link.cause())
+        }
+
+        fn backtrace(&self) -> Option<&Backtrace> {
+            Some(self.link.backtrace())
+        }
+
+        fn __private_get_type_id__(&self) -> TypeId {
+            TypeId::of::<Context<D>>()
+        }
+
+        fn __private_get_kind__(&self) -> Option<&dyn Any> {
+            self.context.__private_get_kind__()
+        }
+    }
+
+    impl<D: Display + Send + Sync + 'static> Debug for StackedContext<D> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{:?}\n\n", self.link)?;
+            self.context.fmt_context(f)
+        }
+    }
+
+    impl<D: Display + Send + Sync + 'static> Display for StackedContext<D> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.context.context)
+        }
+    }
+
     enum Either<A, B> {
         This(A),
         That(B),
@@ -148,8 +284,8 @@ with_std! {
 
         fn as_cause(&self) -> Option<&dyn Fail> {
             match *self {
-                Either::This(_)         => None,
-                Either::That(ref error) => Some(error.as_fail())
+                Either::This(_)           => None,
+                Either::That(ref error)   => Some(error.as_fail()),
             }
         }
     }
@@ -166,7 +302,7 @@ with_std! {
     #[test]
     fn test_map() {
         let ctx = Context::new("a string").map(|s| format!("{} with some more stuff", s));
-        assert_eq!(ctx.context, String::from("a string with some more stuff"));
+        assert_eq!(ctx.context, "a string with some more stuff");
     }
 }
 
@@ -178,3 +314,54 @@ where
         Context::new(display)
     }
 }
+
+/// A context value which is formatted only when it is displayed.
+///
+/// `ResultExt::with_context` builds its context as soon as an error occurs,
+/// which is wasted work when the error is handled without being displayed.
+/// A `LazyContext` instead stores a closure, which writes the context to the
+/// formatter each time it is displayed. Create one with
+/// `ResultExt::lazy_context`:
+///
+/// ```
+/// use failure::ResultExt;
+///
+/// let line = 7;
+/// let err = "x".parse::<u32>()
+///     .lazy_context(move |f| write!(f, "invalid number on line {}", line))
+///     .unwrap_err();
+/// assert_eq!(err.to_string(), "invalid number on line 7");
+/// ```
+pub struct LazyContext<F> {
+    f: F,
+}
+
+impl<F> LazyContext<F>
+where
+    F: Fn(&mut fmt::Formatter) -> fmt::Result + Send + Sync + 'static,
+{
+    /// Creates a new lazy context from a closure writing it to a formatter.
+    pub fn new(f: F) -> LazyContext<F> {
+        LazyContext { f }
+    }
+}
+
+impl<F> Display for LazyContext<F>
+where
+    F: Fn(&mut fmt::Formatter) -> fmt::Result,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (self.f)(f)
+    }
+}
+
+impl<F> Debug for LazyContext<F>
+where
+    F: Fn(&mut fmt::Formatter) -> fmt::Result,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LazyContext(\"")?;
+        (self.f)(f)?;
+        write!(f, "\")")
+    }
+}
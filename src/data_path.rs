@@ -0,0 +1,223 @@
+use core::fmt::{self, Debug, Display};
+use std::borrow::Cow;
+
+use {Backtrace, Error, Fail};
+
+/// One step of a `DataPath`: a field name or an index.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    /// A field of an object, such as `email`.
+    Field(Cow<'static, str>),
+    /// An element of an array, such as `3`.
+    Index(usize),
+}
+
+impl From<&'static str> for PathSegment {
+    fn from(field: &'static str) -> PathSegment {
+        PathSegment::Field(Cow::Borrowed(field))
+    }
+}
+
+impl From<String> for PathSegment {
+    fn from(field: String) -> PathSegment {
+        PathSegment::Field(Cow::Owned(field))
+    }
+}
+
+impl From<usize> for PathSegment {
+    fn from(index: usize) -> PathSegment {
+        PathSegment::Index(index)
+    }
+}
+
+/// The location of a failure within nested data, such as `users[3].email`.
+///
+/// The `Display` impl prints the path in dotted form, with indices in
+/// brackets. `to_json_pointer` returns it as a JSON pointer instead.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DataPath {
+    segments: Vec<PathSegment>,
+}
+
+impl DataPath {
+    /// Creates an empty path.
+    pub fn new() -> DataPath {
+        DataPath { segments: Vec::new() }
+    }
+
+    /// Returns the segments of this path, from the outermost to the
+    /// innermost.
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    /// Returns true if this path has no segments.
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Adds a segment to the outer end of this path.
+    pub fn push_front<S: Into<PathSegment>>(&mut self, segment: S) {
+        self.segments.insert(0, segment.into());
+    }
+
+    /// Adds a segment to the inner end of this path.
+    pub fn push_back<S: Into<PathSegment>>(&mut self, segment: S) {
+        self.segments.push(segment.into());
+    }
+
+    /// Returns this path as a JSON pointer (RFC 6901), such as
+    /// `/users/3/email`.
+    pub fn to_json_pointer(&self) -> String {
+        let mut pointer = String::new();
+        for segment in &self.segments {
+            pointer.push('/');
+            match *segment {
+                PathSegment::Field(ref field) => {
+                    pointer.push_str(&field.replace('~', "~0").replace('/', "~1"))
+                }
+                PathSegment::Index(index) => pointer.push_str(&index.to_string()),
+            }
+        }
+        pointer
+    }
+}
+
+impl Display for DataPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            match *segment {
+                PathSegment::Field(ref field) if i == 0 => write!(f, "{}", field)?,
+                PathSegment::Field(ref field) => write!(f, ".{}", field)?,
+                PathSegment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An error located at a `DataPath` within nested data.
+///
+/// This is created by `Error::at` and `DataPathExt::at`. Segments added to an
+/// error which is already a `PathContext` are added to its path, rather than
+/// wrapping it again, so that an error which bubbles up through several
+/// levels of nested data ends up with a single path.
+///
+/// A segment added above another context starts a new `PathContext`, and
+/// `Error::data_path` joins the paths of all of them.
+///
+/// Like other contexts, the `Display` impl prints only the path, such as
+/// `users[3].email`, and the underlying error is the cause.
+pub struct PathContext {
+    path: DataPath,
+    error: Error,
+}
+
+impl PathContext {
+    /// Returns the path at which the error occurred.
+    pub fn path(&self) -> &DataPath {
+        &self.path
+    }
+
+    /// Returns the underlying error.
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+
+    /// Returns the underlying error, discarding the path.
+    pub fn into_error(self) -> Error {
+        self.error
+    }
+
+    pub(crate) fn push_front(error: Error, segment: PathSegment) -> Error {
+        let mut error = error;
+        if let Some(context) = error.downcast_mut::<PathContext>() {
+            context.path.push_front(segment);
+            return error;
+        }
+        let mut path = DataPath::new();
+        path.push_front(segment);
+        Error::from(PathContext { path, error })
+    }
+}
+
+impl Fail for PathContext {
+    fn name(&self) -> Option<&str> {
+        Some("failure::PathContext")
+    }
+
+    fn cause(&self) -> Option<&dyn Fail> {
+        Some(self.error.as_fail())
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        Some(self.error.backtrace())
+    }
+}
+
+impl Debug for PathContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}\n\nat {}", self.error, self.path)
+    }
+}
+
+impl Display for PathContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&self.path, f)
+    }
+}
+
+/// Extension methods for `Result`s whose error can be converted into an
+/// `Error`.
+pub trait DataPathExt<T> {
+    /// Records that the error occurred at a field or index of nested data.
+    ///
+    /// See `Error::at`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use failure::{DataPathExt, Error};
+    ///
+    /// fn parse_port(port: &str) -> Result<u16, Error> {
+    ///     Ok(port.parse::<u16>()?)
+    /// }
+    ///
+    /// let ports = ["80", "http"];
+    /// let err = ports.iter()
+    ///     .enumerate()
+    ///     .map(|(i, port)| parse_port(port).at(i))
+    ///     .collect::<Result<Vec<_>, _>>()
+    ///     .at("ports")
+    ///     .unwrap_err();
+    /// assert_eq!(err.data_path().unwrap().to_string(), "ports[1]");
+    /// ```
+    fn at<S: Into<PathSegment>>(self, segment: S) -> Result<T, Error>;
+}
+
+impl<T, E: Into<Error>> DataPathExt<T> for Result<T, E> {
+    fn at<S: Into<PathSegment>>(self, segment: S) -> Result<T, Error> {
+        self.map_err(|error| error.into().at(segment))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DataPath, PathSegment};
+
+    #[test]
+    fn display_and_json_pointer() {
+        let mut path = DataPath::new();
+        path.push_back("users");
+        path.push_back(3);
+        path.push_back(String::from("a/b~c"));
+        assert_eq!(path.to_string(), "users[3].a/b~c");
+        assert_eq!(path.to_json_pointer(), "/users/3/a~1b~0c");
+
+        path.push_front(0);
+        assert_eq!(path.to_string(), "[0].users[3].a/b~c");
+        assert_eq!(path.segments()[0], PathSegment::Index(0));
+        assert_eq!(DataPath::new().to_json_pointer(), "");
+    }
+}
+
@@ -1,50 +1,231 @@
-use core::any::TypeId;
+use core::any::{Any, TypeId};
+use core::fmt::{self, Debug};
+use core::mem;
+use core::ptr::NonNull;
+use core::fmt::Display;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
 
 use Fail;
 use backtrace::Backtrace;
+use context::StackedContext;
+use super::Error;
 
 pub(crate) struct ErrorImpl {
-    inner: Box<Inner<dyn Fail>>,
+    // Owned like a `Box`, but kept as a raw pointer because the frames on the
+    // context stack hold `Link`s pointing back into this allocation.
+    inner: NonNull<Inner<dyn Fail>>,
 }
 
 struct Inner<F: ?Sized + Fail> {
     backtrace: Backtrace,
+    suppressed: Vec<Error>,
+    frames: Vec<Frame>,
     pub(crate) failure: F,
 }
 
+// `ErrorImpl` owns its `Inner` like a `Box` would, and everything in it is
+// `Send` and `Sync`.
+unsafe impl Send for ErrorImpl { }
+unsafe impl Sync for ErrorImpl { }
+
+/// A context pushed onto the stack of an `Error` by `Error::wrap`.
+///
+/// Frames are the outer layers of the error: the last frame is the failure
+/// the `Error` appears to be, and each frame's cause is the frame below it,
+/// down to the failure the `Error` was created from. A frame is never moved
+/// out of its `Error` while it holds its `Link`.
+///
+/// Contexts given as string literals or `String`s are stored in place, so
+/// that pushing them does not allocate, and contexts of other types are boxed.
+enum Frame {
+    Str(StackedContext<&'static str>),
+    String(StackedContext<String>),
+    Boxed(Box<dyn Stacked>),
+}
+
+/// A `StackedContext` of any type.
+pub(crate) trait Stacked: Send + Sync + 'static {
+    fn as_fail(&self) -> &dyn Fail;
+
+    fn as_fail_mut(&mut self) -> &mut dyn Fail;
+
+    /// Converts this context, removed from the stack, into the `Context` it
+    /// appears to be, with the rest of the error as its cause.
+    fn detach(self: Box<Self>, rest: Error) -> Box<dyn Any>;
+}
+
+impl Frame {
+    fn new<D: Display + Send + Sync + 'static>(context: D, link: Link) -> Frame {
+        let mut context = Some(context);
+        let any = &mut context as &mut dyn Any;
+        if let Some(context) = any.downcast_mut::<Option<&'static str>>() {
+            return Frame::Str(StackedContext::new(context.take().unwrap(), link));
+        }
+        if let Some(context) = any.downcast_mut::<Option<String>>() {
+            return Frame::String(StackedContext::new(context.take().unwrap(), link));
+        }
+        Frame::Boxed(Box::new(StackedContext::new(context.unwrap(), link)))
+    }
+
+    fn as_fail(&self) -> &dyn Fail {
+        match *self {
+            Frame::Str(ref context) => context,
+            Frame::String(ref context) => context,
+            Frame::Boxed(ref context) => context.as_fail(),
+        }
+    }
+
+    fn as_fail_mut(&mut self) -> &mut dyn Fail {
+        match *self {
+            Frame::Str(ref mut context) => context,
+            Frame::String(ref mut context) => context,
+            Frame::Boxed(ref mut context) => context.as_fail_mut(),
+        }
+    }
+
+    fn detach(self, rest: Error) -> Box<dyn Any> {
+        match self {
+            Frame::Str(context) => Box::new(context).detach(rest),
+            Frame::String(context) => Box::new(context).detach(rest),
+            Frame::Boxed(context) => context.detach(rest),
+        }
+    }
+}
+
+/// The link from a frame to the part of the error below it.
+pub(crate) struct Link {
+    inner: NonNull<Inner<dyn Fail>>,
+    index: usize,
+}
+
+// A link is only read through the frame holding it, which is owned by the
+// allocation the link points to, so that allocation outlives every read.
+// Links only give out shared references to `Fail`s, which are `Sync`.
+unsafe impl Send for Link { }
+unsafe impl Sync for Link { }
+
+impl Link {
+    fn inner(&self) -> &Inner<dyn Fail> {
+        unsafe { &*self.inner.as_ptr() }
+    }
+
+    pub(crate) fn cause(&self) -> &dyn Fail {
+        let inner = self.inner();
+        match self.index {
+            0 => &inner.failure,
+            index => inner.frames[index - 1].as_fail(),
+        }
+    }
+
+    pub(crate) fn backtrace(&self) -> &Backtrace {
+        let inner = self.inner();
+        inner.failure.backtrace().unwrap_or(&inner.backtrace)
+    }
+}
+
+impl Debug for Link {
+    // Matches the `Debug` output of the `Error` a nested context would hold.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let inner = self.inner();
+        if self.index > 0 || inner.backtrace.is_none() {
+            Debug::fmt(self.cause(), f)
+        } else {
+            write!(f, "{:?}\n\n{:?}", self.cause(), inner.backtrace)
+        }
+    }
+}
+
 impl<F: Fail> From<F> for ErrorImpl {
     fn from(failure: F) -> ErrorImpl {
         let inner: Inner<F> = {
             let backtrace = if failure.backtrace().is_none() {
                 Backtrace::new()
             } else { Backtrace::none() };
-            Inner { failure, backtrace }
+            Inner { failure, suppressed: Vec::new(), frames: Vec::new(), backtrace }
         };
-        ErrorImpl { inner: Box::new(inner) }
+        let inner: Box<Inner<dyn Fail>> = Box::new(inner);
+        ErrorImpl { inner: unsafe { NonNull::new_unchecked(Box::into_raw(inner)) } }
     }
 }
 
 impl ErrorImpl {
+    fn inner(&self) -> &Inner<dyn Fail> {
+        unsafe { &*self.inner.as_ptr() }
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner<dyn Fail> {
+        unsafe { &mut *self.inner.as_ptr() }
+    }
+
     pub(crate) fn failure(&self) -> &dyn Fail {
-        &self.inner.failure
+        let inner = self.inner();
+        match inner.frames.last() {
+            Some(frame) => frame.as_fail(),
+            None => &inner.failure,
+        }
     }
 
     pub(crate) fn failure_mut(&mut self) -> &mut dyn Fail {
-        &mut self.inner.failure
+        let inner = self.inner_mut();
+        match inner.frames.last_mut() {
+            Some(frame) => frame.as_fail_mut(),
+            None => &mut inner.failure,
+        }
     }
 
+    /// Returns the backtrace captured when this error was created, which is
+    /// only printed by `Debug` when there is no context stack.
     pub(crate) fn backtrace(&self) -> &Backtrace {
-        &self.inner.backtrace
+        &self.inner().backtrace
     }
 
-    pub(crate) fn downcast<T: Fail>(self) -> Result<T, ErrorImpl> {
-        if self.failure().__private_get_type_id__() == TypeId::of::<T>() {
-            let ErrorImpl { inner } = self;
-            let casted = unsafe { Box::from_raw(Box::into_raw(inner) as *mut Inner<T>) };
-            let Inner { backtrace:_, failure } = *casted;
-            Ok(failure)
-        } else {
-            Err(self)
+    pub(crate) fn has_frames(&self) -> bool {
+        !self.inner().frames.is_empty()
+    }
+
+    /// Pushes a context onto the stack, linked to the current top of the
+    /// error.
+    pub(crate) fn push_context<D: Display + Send + Sync + 'static>(&mut self, context: D) {
+        let link = Link { inner: self.inner, index: self.inner().frames.len() };
+        let frame = Frame::new(context, link);
+        self.inner_mut().frames.push(frame);
+    }
+
+    pub(crate) fn suppressed(&self) -> &[Error] {
+        &self.inner().suppressed
+    }
+
+    pub(crate) fn suppress(&mut self, error: Error) {
+        self.inner_mut().suppressed.push(error);
+    }
+
+    /// Downcasts the failure, returning it with the suppressed errors which
+    /// are not kept by it. A context popped off the stack keeps them in the
+    /// rest of the error, its cause.
+    pub(crate) fn downcast<T: Fail>(mut self) -> Result<(T, Vec<Error>), ErrorImpl> {
+        if self.failure().__private_get_type_id__() != TypeId::of::<T>() {
+            return Err(self);
+        }
+        match self.inner_mut().frames.pop() {
+            Some(frame) => match frame.detach(Error { imp: self }).downcast::<T>() {
+                Ok(failure) => Ok((*failure, Vec::new())),
+                Err(_) => unreachable!("a frame detached into another type"),
+            },
+            None => {
+                let inner = self.inner.as_ptr();
+                mem::forget(self);
+                let casted = unsafe { Box::from_raw(inner as *mut Inner<T>) };
+                let Inner { backtrace:_, suppressed, frames:_, failure } = *casted;
+                Ok((failure, suppressed))
+            }
         }
     }
 }
+
+impl Drop for ErrorImpl {
+    fn drop(&mut self) {
+        unsafe { drop(Box::from_raw(self.inner.as_ptr())) }
+    }
+}
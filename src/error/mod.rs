@@ -1,16 +1,25 @@
+use core::any::Any;
 use core::fmt::{self, Display, Debug};
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io;
 
-use {Causes, Fail};
+use {Causes, Fail, Field};
 use backtrace::Backtrace;
 use context::Context;
 use compat::Compat;
 
 #[cfg(feature = "std")]
 use box_std::BoxStd;
+#[cfg(feature = "std")]
+use data_path::{DataPath, PathContext, PathSegment};
+#[cfg(feature = "std")]
+use snapshot::Snapshot;
 
 #[cfg_attr(feature = "small-error", path = "./error_impl_small.rs")]
 mod error_impl;
 use self::error_impl::ErrorImpl;
+pub(crate) use self::error_impl::{Link, Stacked};
 
 #[cfg(feature = "std")]
 use std::error::Error as StdError;
@@ -32,8 +41,49 @@ pub struct Error {
 
 impl<F: Fail> From<F> for Error {
     fn from(failure: F) -> Error {
+        // Failures wrapping an `Error`, as made by `compat` and
+        // `into_io_error`, are unwrapped so that converting them back gives
+        // the original `Error` rather than adding a layer each time.
+        let mut failure = Some(failure);
+        let compat = (&mut failure as &mut dyn Any)
+            .downcast_mut::<Option<Compat<Error>>>()
+            .and_then(Option::take);
+        if let Some(compat) = compat {
+            return compat.into_inner();
+        }
+        #[cfg(feature = "std")]
+        if let Some(io_error) = (&mut failure as &mut dyn Any).downcast_mut::<Option<io::Error>>() {
+            let wraps_error = match io_error.as_ref().and_then(io::Error::get_ref) {
+                Some(inner) => inner.is::<Compat<Error>>(),
+                None => false,
+            };
+            if wraps_error {
+                let inner = io_error.take().and_then(io::Error::into_inner);
+                if let Some(Ok(compat)) = inner.map(|inner| inner.downcast::<Compat<Error>>()) {
+                    return compat.into_inner();
+                }
+            }
+        }
         Error {
-            imp: ErrorImpl::from(failure)
+            imp: ErrorImpl::from(failure.expect("failure was taken"))
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Error> for io::Error {
+    /// Converts an `Error` into an `io::Error`.
+    ///
+    /// If the `Error` is an `io::Error`, it is returned by downcasting.
+    /// Otherwise, this is `into_io_error` with the kind of the first
+    /// `io::Error` in the chain, or `ErrorKind::Other` if there is none.
+    fn from(error: Error) -> io::Error {
+        match error.downcast::<io::Error>() {
+            Ok(io_error) => io_error,
+            Err(error) => {
+                let kind = error.io_error_kind().unwrap_or(io::ErrorKind::Other);
+                error.into_io_error(kind)
+            }
         }
     }
 }
@@ -44,6 +94,11 @@ impl Error {
     /// This method is useful for comparability with code,
     /// which does not use the `Fail` trait.
     ///
+    /// If the box holds an `Error`, as made by converting an `Error` into
+    /// `Box<std::error::Error>`, that `Error` is returned. If it holds an
+    /// `io::Error`, it is converted as with `Error::from`, so that it can be
+    /// downcast. Otherwise, the concrete type of the boxed error is lost.
+    ///
     /// # Example
     ///
     /// ```
@@ -61,7 +116,13 @@ impl Error {
     /// ```
     #[cfg(feature = "std")]
     pub fn from_boxed_compat(err: Box<dyn StdError + Sync + Send + 'static>) -> Error {
-        Error::from(BoxStd(err))
+        match err.downcast::<Compat<Error>>() {
+            Ok(compat) => compat.into_inner(),
+            Err(err) => match err.downcast::<io::Error>() {
+                Ok(io_error) => Error::from(*io_error),
+                Err(err) => Error::from(BoxStd(err)),
+            },
+        }
     }
 
     /// Return a reference to the underlying failure that this `Error`
@@ -92,7 +153,7 @@ impl Error {
     /// be returned. Otherwise, the backtrace will have been constructed at
     /// the point that failure was cast into the `Error` type.
     pub fn backtrace(&self) -> &Backtrace {
-        self.imp.failure().backtrace().unwrap_or(&self.imp.backtrace())
+        self.imp.failure().backtrace().unwrap_or(self.imp.backtrace())
     }
 
     /// Provides context for this `Error`.
@@ -112,6 +173,91 @@ impl Error {
         Context::with_err(context, self)
     }
 
+    /// Wraps this `Error` in a context, returning another `Error`.
+    ///
+    /// This is equivalent to `Error::from(self.context(context))`: the
+    /// resulting error displays as the context, its chain starts with the
+    /// `Context<D>` followed by the chain of this error, and it can be
+    /// downcast to `Context<D>`. However, instead of allocating a new `Error`
+    /// around the context, the context is pushed onto a stack stored in this
+    /// `Error`, so that wrapping an error many times is cheaper. The errors
+    /// suppressed by this `Error` stay with it.
+    ///
+    /// A `Context<D>` borrowed from the returned error with `downcast_ref` or
+    /// `downcast_mut` has no cause of its own; downcast the error by value to
+    /// get the `Context<D>` with the rest of the error as its cause.
+    ///
+    /// ```
+    /// use failure::{err_msg, Context};
+    ///
+    /// let err = err_msg("connection reset")
+    ///     .wrap("fetching index")
+    ///     .wrap("updating registry");
+    /// assert_eq!(err.to_string(), "updating registry");
+    /// assert_eq!(err.iter_chain().count(), 3);
+    /// assert!(err.downcast_ref::<Context<&str>>().is_some());
+    /// ```
+    pub fn wrap<D: Display + Send + Sync + 'static>(mut self, context: D) -> Error {
+        self.imp.push_context(context);
+        self
+    }
+
+    /// Records that this error occurred at a field or index of nested data.
+    ///
+    /// Call this as the error bubbles up, with the innermost segment first.
+    /// The segments are collected into a single `PathContext`, instead of
+    /// each adding a layer to the chain:
+    ///
+    /// ```
+    /// use failure::err_msg;
+    ///
+    /// let err = err_msg("invalid address").at("email").at(3).at("users");
+    /// assert_eq!(err.to_string(), "users[3].email");
+    /// assert_eq!(err.data_path().unwrap().to_json_pointer(), "/users/3/email");
+    /// assert_eq!(err.find_root_cause().to_string(), "invalid address");
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn at<S: Into<PathSegment>>(self, segment: S) -> Error {
+        PathContext::push_front(self, segment.into())
+    }
+
+    /// Returns the path at which this error occurred, joining the paths of
+    /// all the `PathContext`s in its chain, from the outermost to the
+    /// innermost.
+    #[cfg(feature = "std")]
+    pub fn data_path(&self) -> Option<DataPath> {
+        self.iter_chain()
+            .filter_map(|fail| fail.downcast_ref::<PathContext>())
+            .fold(None, |path: Option<DataPath>, context| {
+                let mut path = path.unwrap_or_default();
+                for segment in context.path().segments() {
+                    path.push_back(segment.clone());
+                }
+                Some(path)
+            })
+    }
+
+    /// Attaches a suppressed error to this `Error`.
+    ///
+    /// A suppressed error is a secondary failure which occurred while this
+    /// error was being handled, such as a failed rollback after a failed
+    /// transaction, and which would otherwise have to be thrown away. It does
+    /// not change the chain of this error, but is printed after it by the
+    /// `Debug` impl, and can be retrieved with `suppressed`.
+    ///
+    /// To keep the suppressed errors when downcasting this `Error` by value,
+    /// use `downcast_with_suppressed`.
+    pub fn with_suppressed<E: Into<Error>>(mut self, error: E) -> Error {
+        self.imp.suppress(error.into());
+        self
+    }
+
+    /// Returns the errors suppressed by this `Error`, in the order they were
+    /// attached.
+    pub fn suppressed(&self) -> &[Error] {
+        self.imp.suppressed()
+    }
+
     /// Wraps `Error` in a compatibility type.
     ///
     /// This type implements the `Error` trait from `std::error`. If you need
@@ -121,13 +267,81 @@ impl Error {
         Compat { error: self }
     }
 
+    #[cfg(feature = "std")]
+    /// Wraps this `Error` in an `io::Error` of the given kind.
+    ///
+    /// This is useful to implement traits such as `Read` and `Write` on top
+    /// of code returning `Error`. Converting the `io::Error` back into an
+    /// `Error`, for example with `?`, gives back this `Error` rather than
+    /// wrapping it again:
+    ///
+    /// ```
+    /// use std::io;
+    /// use failure::{err_msg, Error};
+    ///
+    /// let io_error = err_msg("checksum mismatch").into_io_error(io::ErrorKind::InvalidData);
+    /// assert_eq!(io_error.kind(), io::ErrorKind::InvalidData);
+    ///
+    /// let error = Error::from(io_error);
+    /// assert!(error.is_message());
+    /// ```
+    pub fn into_io_error(self, kind: io::ErrorKind) -> io::Error {
+        io::Error::new(kind, self.compat())
+    }
+
+    #[cfg(feature = "std")]
+    /// Returns the kind of the first `io::Error`, or `Snapshot` of one, in
+    /// the chain of this error.
+    pub fn io_error_kind(&self) -> Option<io::ErrorKind> {
+        self.iter_chain()
+            .filter_map(|fail| match fail.downcast_ref::<io::Error>() {
+                Some(io_error) => Some(io_error.kind()),
+                None => fail.downcast_ref::<Snapshot>().and_then(Snapshot::io_error_kind),
+            })
+            .next()
+    }
+
+    #[cfg(feature = "std")]
+    /// Returns the OS error code of the first `io::Error` in the chain of
+    /// this error, if it has one.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.find_io_error().and_then(io::Error::raw_os_error)
+    }
+
+    #[cfg(feature = "std")]
+    fn find_io_error(&self) -> Option<&io::Error> {
+        self.iter_chain().filter_map(|fail| fail.downcast_ref::<io::Error>()).next()
+    }
+
     /// Attempts to downcast this `Error` to a particular `Fail` type.
     ///
     /// This downcasts by value, returning an owned `T` if the underlying
     /// failure is of the type `T`. For this reason it returns a `Result` - in
     /// the case that the underlying error is of a different type, the
     /// original `Error` is returned.
+    ///
+    /// The errors suppressed by this `Error` are dropped, unless the failure
+    /// is a context added by `wrap`, whose cause keeps them. Use
+    /// `downcast_with_suppressed` to keep them in any case.
     pub fn downcast<T: Fail>(self) -> Result<T, Error> {
+        self.downcast_with_suppressed().map(|(failure, _)| failure)
+    }
+
+    /// Attempts to downcast this `Error` to a particular `Fail` type, also
+    /// returning the errors it suppressed.
+    ///
+    /// This is like `downcast`, but the suppressed errors which the `T` does
+    /// not keep are returned alongside it instead of being dropped:
+    ///
+    /// ```
+    /// use failure::{err_msg, ErrorMessage};
+    ///
+    /// let err = err_msg("commit failed").with_suppressed(err_msg("rollback failed"));
+    /// let (message, suppressed) = err.downcast_with_suppressed::<ErrorMessage>().unwrap();
+    /// assert_eq!(message.to_string(), "commit failed");
+    /// assert_eq!(suppressed[0].to_string(), "rollback failed");
+    /// ```
+    pub fn downcast_with_suppressed<T: Fail>(self) -> Result<(T, Vec<Error>), Error> {
         self.imp.downcast().map_err(|imp| Error { imp })
     }
 
@@ -137,11 +351,39 @@ impl Error {
         self.as_fail().find_root_cause()
     }
 
+    /// Returns the first error code found in the chain of this error.
+    pub fn code(&self) -> Option<&str> {
+        self.as_fail().find_code()
+    }
+
+    /// Returns true if the first classified failure in the chain of this
+    /// error is transient, so that the operation which failed may succeed if
+    /// it is retried.
+    ///
+    /// Errors with no classified failure in their chain are not transient.
+    /// See `Fail::is_transient`.
+    pub fn is_transient(&self) -> bool {
+        self.as_fail().find_transient().unwrap_or(false)
+    }
+
+    /// Returns an iterator over the structured fields of every failure in the
+    /// chain of this error, starting with the outermost failure.
+    pub fn fields(&self) -> impl Iterator<Item = &Field> {
+        self.iter_chain().flat_map(|fail| fail.fields())
+    }
+
+    /// Returns the first kind of type `K` found in the chain of this error.
+    ///
+    /// See `Fail::find_kind` for where kinds come from.
+    pub fn find_kind<K: Copy + 'static>(&self) -> Option<K> {
+        self.as_fail().find_kind()
+    }
+
     /// Returns a iterator over the causes of this error with the cause
     /// of the fail as the first item and the `root_cause` as the final item.
     ///
     /// Use `iter_chain` to also include the fail of this error itself.
-    pub fn iter_causes(&self) -> Causes {
+    pub fn iter_causes(&self) -> Causes<'_> {
         self.as_fail().iter_causes()
     }
 
@@ -151,7 +393,7 @@ impl Error {
     /// This means that the chain also includes the fail itself which
     /// means that it does *not* start with `cause`.  To skip the outermost
     /// fail use `iter_causes` instead.
-    pub fn iter_chain(&self) -> Causes {
+    pub fn iter_chain(&self) -> Causes<'_> {
         self.as_fail().iter_chain()
     }
 
@@ -171,6 +413,16 @@ impl Error {
         self.imp.failure_mut().downcast_mut()
     }
 
+    /// Returns true if this `Error` is just a message, as created by `err_msg`
+    /// or `format_err!`, rather than a typed failure.
+    ///
+    /// A message given as a string can be retrieved by downcasting to
+    /// `ErrorMessage`, and a message of another type `D` by downcasting to
+    /// `ErrorMessage<D>`.
+    pub fn is_message(&self) -> bool {
+        self.as_fail().__private_is_message__()
+    }
+
     /// Deprecated alias to `find_root_cause`.
     #[deprecated(since = "0.1.2", note = "please use the 'find_root_cause()' method instead")]
     pub fn root_cause(&self) -> &dyn Fail {
@@ -179,7 +431,7 @@ impl Error {
 
     /// Deprecated alias to `iter_causes`.
     #[deprecated(since = "0.1.2", note = "please use the 'iter_chain()' method instead")]
-    pub fn causes(&self) -> Causes {
+    pub fn causes(&self) -> Causes<'_> {
         Causes { fail: Some(self.as_fail()) }
     }
 }
@@ -193,11 +445,15 @@ impl Display for Error {
 impl Debug for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let backtrace = self.imp.backtrace();
-        if backtrace.is_none() {
-            Debug::fmt(&self.imp.failure(), f)
+        if backtrace.is_none() || self.imp.has_frames() {
+            Debug::fmt(&self.imp.failure(), f)?;
         } else {
-            write!(f, "{:?}\n\n{:?}", &self.imp.failure(), backtrace)
+            write!(f, "{:?}\n\n{:?}", &self.imp.failure(), backtrace)?;
+        }
+        for suppressed in self.suppressed() {
+            write!(f, "\n\nSuppressed: {:?}", suppressed)?;
         }
+        Ok(())
     }
 }
 
@@ -207,10 +463,11 @@ impl AsRef<dyn Fail> for Error {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod test {
     use std::io;
     use super::Error;
+    use {Context, Fail};
 
     fn assert_just_data<T: Send + Sync + 'static>() { }
 
@@ -228,7 +485,6 @@ mod test {
         assert_eq!(format!("{:?}", io_error), format!("{:?}", error));
         assert_eq!(format!("{}", io_error), format!("{}", error));
         drop(error);
-        assert!(true);
     }
 
     #[test]
@@ -245,4 +501,79 @@ mod test {
         let real_io_error = error.downcast::<io::Error>().unwrap();
         assert_eq!(real_io_error.to_string(), "test");
     }
+
+    fn chain(error: &Error) -> Vec<(String, Option<String>)> {
+        error.iter_chain()
+            .map(|fail| (fail.to_string(), fail.name().map(|name| name.to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn wrap_matches_nested_context() {
+        let nested = || -> Error {
+            let error: Error = io::Error::new(io::ErrorKind::NotFound, "test").into();
+            let error: Error = error.context("reading").into();
+            error.context(String::from("loading")).into()
+        };
+        let wrapped = || -> Error {
+            let error: Error = io::Error::new(io::ErrorKind::NotFound, "test").into();
+            error.wrap("reading").wrap(String::from("loading"))
+        };
+
+        assert_eq!(chain(&nested()), chain(&wrapped()));
+        assert_eq!(format!("{}", nested()), format!("{}", wrapped()));
+        // Skip any backtrace, which differs by where the errors were created.
+        let debug = |error: Error| {
+            let debug = format!("{:?}", error);
+            let first = debug.lines().next().unwrap().to_string();
+            let last: Vec<_> = debug.rsplit("\n\n").take(2).map(|s| s.to_string()).collect();
+            (first, last)
+        };
+        assert_eq!(debug(nested()), debug(wrapped()));
+        assert!(wrapped().downcast_ref::<Context<String>>().is_some());
+        assert!(wrapped().downcast_ref::<io::Error>().is_none());
+        assert_eq!(wrapped().find_root_cause().to_string(), "test");
+    }
+
+    #[test]
+    fn wrapped_context_can_be_downcast() {
+        let error: Error = io::Error::new(io::ErrorKind::NotFound, "test").into();
+        let mut error = error.wrap("reading").wrap("loading");
+        assert_eq!(*error.downcast_mut::<Context<&str>>().unwrap().get_context(), "loading");
+
+        let context = error.downcast::<Context<&str>>().unwrap();
+        assert_eq!(*context.get_context(), "loading");
+        let rest: Vec<_> = (&context as &dyn Fail).iter_causes().map(|fail| fail.to_string()).collect();
+        assert_eq!(rest, ["reading", "test"]);
+
+        let error: Error = context.into();
+        let error = error.wrap("retrying");
+        assert_eq!(error.iter_chain().count(), 4);
+        let error = error.downcast::<io::Error>().unwrap_err();
+        let context = error.downcast::<Context<&str>>().unwrap();
+        assert_eq!(context.to_string(), "retrying");
+        assert_eq!((&context as &dyn Fail).iter_causes().count(), 3);
+
+        // Contexts other than strings are boxed on the stack.
+        let mut error = Error::from(context).wrap(404u16);
+        assert_eq!(*error.downcast_mut::<Context<u16>>().unwrap().get_context(), 404);
+        let context = error.downcast::<Context<u16>>().unwrap();
+        assert_eq!(context.cause().unwrap().to_string(), "retrying");
+    }
+
+    #[test]
+    fn wrapped_context_can_be_moved_out() {
+        let error: Error = io::Error::new(io::ErrorKind::NotFound, "test").into();
+        let mut error = error.wrap("reading").wrap("loading");
+        let context = {
+            let context = error.downcast_mut::<Context<&str>>().unwrap();
+            ::core::mem::replace(context, Context::new("replaced"))
+        };
+        let chain: Vec<_> = error.iter_chain().map(|fail| fail.to_string()).collect();
+        assert_eq!(chain, ["replaced", "reading", "test"]);
+        drop(error);
+        // The link to the rest of the error stays in the error.
+        assert!((&context as &dyn Fail).cause().is_none());
+        assert_eq!(context.to_string(), "loading");
+    }
 }
@@ -1,4 +1,7 @@
+use core::any::Any;
 use core::fmt::{self, Display, Debug};
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
 
 use Fail;
 use Error;
@@ -8,21 +11,68 @@ use Error;
 /// This is a convenient way to turn a string into an error value that
 /// can be passed around, if you do not want to create a new `Fail` type for
 /// this use case.
+///
+/// String literals and `String`s are stored as an `ErrorMessage`, as they
+/// are. Messages of other types are stored as an `ErrorMessage<D>`, and
+/// formatted only when they are displayed.
 pub fn err_msg<D: Display + Debug + Sync + Send + 'static>(msg: D) -> Error {
-    Error::from(ErrorMessage { msg })
+    let mut msg = Some(msg);
+    let any = &mut msg as &mut dyn Any;
+    if let Some(msg) = any.downcast_mut::<Option<&'static str>>() {
+        return Error::from(ErrorMessage::new(msg.take().unwrap()));
+    }
+    if let Some(msg) = any.downcast_mut::<Option<String>>() {
+        return Error::from(ErrorMessage::new(msg.take().unwrap()));
+    }
+    Error::from(ErrorMessage { msg: msg.unwrap() })
 }
 
 /// A `Fail` type that just contains an error message. You can construct
-/// this from the `err_msg` function.
-#[derive(Debug)]
-struct ErrorMessage<D: Display + Debug + Sync + Send + 'static> {
+/// this from the `err_msg` function or the `format_err!` macro.
+///
+/// Messages given as strings have the default type `ErrorMessage`, which
+/// holds a `Cow<'static, str>`, so a message built from a string literal, or
+/// by `format_err!` without any arguments, does not allocate. A message of
+/// another type `D` is kept in an `ErrorMessage<D>`, without boxing it.
+pub struct ErrorMessage<D: Display + Debug + Sync + Send + 'static = Cow<'static, str>> {
     msg: D,
 }
 
+impl ErrorMessage {
+    /// Creates a new message from a string literal or a `String`.
+    pub fn new<M: Into<Cow<'static, str>>>(msg: M) -> ErrorMessage {
+        ErrorMessage { msg: msg.into() }
+    }
+}
+
+impl<D: Display + Debug + Sync + Send + 'static> ErrorMessage<D> {
+    /// Returns the message, formatting it if it was not given as a string.
+    pub fn message(&self) -> Cow<'_, str> {
+        match (&self.msg as &dyn Any).downcast_ref::<Cow<'static, str>>() {
+            Some(msg) => Cow::Borrowed(msg),
+            None => Cow::Owned(self.msg.to_string()),
+        }
+    }
+
+    /// Converts this into its message, formatting it if it was not given as
+    /// a string.
+    pub fn into_message(self) -> Cow<'static, str> {
+        let mut msg = Some(self.msg);
+        if let Some(msg) = (&mut msg as &mut dyn Any).downcast_mut::<Option<Cow<'static, str>>>() {
+            return msg.take().unwrap();
+        }
+        Cow::Owned(msg.unwrap().to_string())
+    }
+}
+
 impl<D: Display + Debug + Sync + Send + 'static> Fail for ErrorMessage<D> {
     fn name(&self) -> Option<&str> {
         Some("failure::ErrorMessage")
     }
+
+    fn __private_is_message__(&self) -> bool {
+        true
+    }
 }
 
 impl<D: Display + Debug + Sync + Send + 'static> Display for ErrorMessage<D> {
@@ -30,3 +80,39 @@ impl<D: Display + Debug + Sync + Send + 'static> Display for ErrorMessage<D> {
         Display::fmt(&self.msg, f)
     }
 }
+
+impl<D: Display + Debug + Sync + Send + 'static> Debug for ErrorMessage<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ErrorMessage").field("msg", &self.msg).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::borrow::Cow;
+    use alloc::string::String;
+
+    use super::{err_msg, ErrorMessage};
+
+    #[test]
+    fn static_messages_are_borrowed() {
+        let err = err_msg("static message");
+        let msg = err.downcast::<ErrorMessage>().unwrap();
+        match msg.into_message() {
+            Cow::Borrowed(msg) => assert_eq!(msg, "static message"),
+            Cow::Owned(_) => panic!("static message was copied"),
+        }
+    }
+
+    #[test]
+    fn other_messages_are_displayed() {
+        let err = err_msg(String::from("owned message"));
+        assert_eq!(err.downcast_ref::<ErrorMessage>().unwrap().message(), "owned message");
+
+        let err = err_msg(42);
+        assert!(err.is_message());
+        let msg = err.downcast_ref::<ErrorMessage<i32>>().unwrap();
+        assert_eq!(format!("{:?}", msg), "ErrorMessage { msg: 42 }");
+        assert_eq!(msg.message(), "42");
+    }
+}
@@ -0,0 +1,156 @@
+/// The long-form explanation of an error code.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Explanation {
+    /// The error code, such as `E0001`.
+    pub code: &'static str,
+    /// The explanation of the error code, usually written in Markdown.
+    pub text: &'static str,
+}
+
+/// A type whose error codes can be explained.
+///
+/// This is implemented by `#[derive(Fail)]` for failures with
+/// `#[fail(code = "...")]` attributes. The explanation of each code is taken
+/// from its `#[fail(explain = "...")]` attribute, or else from the doc comment
+/// of the struct or variant declaring it.
+pub trait Explain {
+    /// Returns the explanations of the error codes of this type.
+    fn explanations() -> &'static [Explanation];
+}
+
+with_std! {
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::io;
+    use std::path::Path;
+
+    /// A registry of explanations, keyed by error code.
+    ///
+    /// Explanations are registered from types implementing `Explain`, or
+    /// inserted directly. They can be written out to a directory with one
+    /// `<code>.md` file per error code, which is what the bundled
+    /// `failure-explain` binary reads:
+    ///
+    /// ```text
+    /// $ failure-explain --dir docs/errors E1042
+    /// ```
+    #[derive(Debug, Clone, Default)]
+    pub struct Explanations {
+        entries: BTreeMap<String, String>,
+    }
+
+    impl Explanations {
+        /// Creates an empty registry.
+        pub fn new() -> Explanations {
+            Explanations::default()
+        }
+
+        /// Registers the explanations of every error code of `T`.
+        pub fn register<T: Explain>(&mut self) -> &mut Explanations {
+            for explanation in T::explanations() {
+                self.insert(explanation.code, explanation.text);
+            }
+            self
+        }
+
+        /// Inserts the explanation of an error code, replacing any previous
+        /// explanation of the same code.
+        pub fn insert<C, T>(&mut self, code: C, text: T) -> &mut Explanations
+        where
+            C: Into<String>,
+            T: Into<String>,
+        {
+            self.entries.insert(code.into(), text.into());
+            self
+        }
+
+        /// Returns the explanation of an error code.
+        pub fn get(&self, code: &str) -> Option<&str> {
+            self.entries.get(code.trim()).map(|text| &text[..])
+        }
+
+        /// Returns an iterator over the error codes and their explanations,
+        /// ordered by code.
+        pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+            self.entries.iter().map(|(code, text)| (&code[..], &text[..]))
+        }
+
+        /// Loads every `<code>.md` file in `dir` as the explanation of that
+        /// error code.
+        ///
+        /// An empty file is an error of kind `InvalidData`, rather than an
+        /// empty explanation.
+        pub fn load_dir<P: AsRef<Path>>(&mut self, dir: P) -> io::Result<&mut Explanations> {
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.extension() != Some("md".as_ref()) {
+                    continue;
+                }
+                if let Some(code) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    let text = fs::read_to_string(&path)?;
+                    if text.trim().is_empty() {
+                        let message = format!("{} has no explanation", path.display());
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+                    }
+                    self.entries.insert(code.to_string(), text);
+                }
+            }
+            Ok(self)
+        }
+
+        /// Writes each explanation to a `<code>.md` file in `dir`, creating
+        /// the directory if needed.
+        pub fn write_dir<P: AsRef<Path>>(&self, dir: P) -> io::Result<()> {
+            let dir = dir.as_ref();
+            fs::create_dir_all(dir)?;
+            for (code, text) in &self.entries {
+                fs::write(dir.join(format!("{}.md", code)), text)?;
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::{Explain, Explanation, Explanations};
+
+        struct Codes;
+
+        impl Explain for Codes {
+            fn explanations() -> &'static [Explanation] {
+                const EXPLANATIONS: &[Explanation] = &[
+                    Explanation { code: "E0001", text: "The first error." },
+                    Explanation { code: "E0002", text: "The second error." },
+                ];
+                EXPLANATIONS
+            }
+        }
+
+        #[test]
+        fn register_and_get() {
+            let mut explanations = Explanations::new();
+            explanations.register::<Codes>().insert("E0003", "The third error.");
+            assert_eq!(explanations.get("E0001"), Some("The first error."));
+            assert_eq!(explanations.get(" E0003\n"), Some("The third error."));
+            assert_eq!(explanations.get("E0004"), None);
+            assert_eq!(explanations.iter().count(), 3);
+        }
+
+        #[test]
+        fn round_trip_dir() {
+            let dir = ::std::env::temp_dir().join(format!("failure-explain-{}", ::std::process::id()));
+            let mut explanations = Explanations::new();
+            explanations.register::<Codes>();
+            explanations.write_dir(&dir).unwrap();
+
+            let mut loaded = Explanations::new();
+            loaded.load_dir(&dir).unwrap();
+            assert_eq!(loaded.get("E0002"), Some("The second error."));
+
+            ::std::fs::write(dir.join("E0003.md"), "\n").unwrap();
+            let err = Explanations::new().load_dir(&dir).unwrap_err();
+            assert_eq!(err.kind(), ::std::io::ErrorKind::InvalidData);
+            ::std::fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use core::fmt::{self, Debug, Display};
+#[cfg(feature = "alloc")]
+use alloc::string::{String, ToString};
+
+/// A structured key-value field attached to a failure.
+///
+/// Fields carry data about a failure, such as the path of a file or the
+/// number of an attempt, without formatting it into the message. They are
+/// attached to a `Context` with `Context::field` or `ResultExt::context_with`,
+/// can be collected from a whole chain with `Error::fields`, and are rendered
+/// as typed values by `JsonReport`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    key: &'static str,
+    value: Value,
+}
+
+impl Field {
+    /// Creates a new field.
+    pub fn new<V: Into<Value>>(key: &'static str, value: V) -> Field {
+        Field { key, value: value.into() }
+    }
+
+    /// Returns the key of this field.
+    pub fn key(&self) -> &'static str {
+        self.key
+    }
+
+    /// Returns the value of this field.
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={:?}", self.key, self.value)
+    }
+}
+
+/// The typed value of a `Field`.
+///
+/// Values are created from booleans, integers, floats and strings through
+/// `From`. Use `get` to inspect the type of a value, for example to render it
+/// as structured data.
+#[derive(Clone, PartialEq)]
+pub struct Value {
+    repr: Repr,
+}
+
+#[derive(Clone, PartialEq)]
+enum Repr {
+    Bool(bool),
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    Static(&'static str),
+    #[cfg(feature = "alloc")]
+    String(String),
+}
+
+/// A borrowed view of a `Value`, by type.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ValueRef<'a> {
+    /// A boolean.
+    Bool(bool),
+    /// A signed integer.
+    I64(i64),
+    /// An unsigned integer.
+    U64(u64),
+    /// A floating point number.
+    F64(f64),
+    /// A string.
+    Str(&'a str),
+}
+
+impl Value {
+    /// Returns a view of this value by type.
+    pub fn get(&self) -> ValueRef<'_> {
+        match self.repr {
+            Repr::Bool(value) => ValueRef::Bool(value),
+            Repr::I64(value) => ValueRef::I64(value),
+            Repr::U64(value) => ValueRef::U64(value),
+            Repr::F64(value) => ValueRef::F64(value),
+            Repr::Static(value) => ValueRef::Str(value),
+            #[cfg(feature = "alloc")]
+            Repr::String(ref value) => ValueRef::Str(value),
+        }
+    }
+
+    /// Returns the string of this value, if it is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self.get() {
+            ValueRef::Str(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Creates a string value from anything that can be displayed.
+    #[cfg(feature = "alloc")]
+    pub fn display<D: Display>(value: D) -> Value {
+        Value { repr: Repr::String(value.to_string()) }
+    }
+}
+
+impl Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.get() {
+            ValueRef::Bool(value) => Debug::fmt(&value, f),
+            ValueRef::I64(value) => Debug::fmt(&value, f),
+            ValueRef::U64(value) => Debug::fmt(&value, f),
+            ValueRef::F64(value) => Debug::fmt(&value, f),
+            ValueRef::Str(value) => Debug::fmt(value, f),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.get() {
+            ValueRef::Bool(value) => Display::fmt(&value, f),
+            ValueRef::I64(value) => Display::fmt(&value, f),
+            ValueRef::U64(value) => Display::fmt(&value, f),
+            ValueRef::F64(value) => Display::fmt(&value, f),
+            ValueRef::Str(value) => Display::fmt(value, f),
+        }
+    }
+}
+
+macro_rules! value_from {
+    ($($ty:ty => $repr:ident,)*) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(value: $ty) -> Value {
+                    Value { repr: Repr::$repr(value.into()) }
+                }
+            }
+        )*
+    }
+}
+
+value_from! {
+    bool => Bool,
+    i8 => I64,
+    i16 => I64,
+    i32 => I64,
+    i64 => I64,
+    u8 => U64,
+    u16 => U64,
+    u32 => U64,
+    u64 => U64,
+    f32 => F64,
+    f64 => F64,
+    &'static str => Static,
+}
+
+impl From<isize> for Value {
+    fn from(value: isize) -> Value {
+        Value { repr: Repr::I64(value as i64) }
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Value {
+        Value { repr: Repr::U64(value as u64) }
+    }
+}
+
+with_alloc! {
+    impl From<String> for Value {
+        fn from(value: String) -> Value {
+            Value { repr: Repr::String(value) }
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use alloc::string::ToString;
+
+        use super::{Field, Value, ValueRef};
+
+        #[test]
+        fn values_keep_their_type() {
+            assert_eq!(Value::from(3u8).get(), ValueRef::U64(3));
+            assert_eq!(Value::from(-3).get(), ValueRef::I64(-3));
+            assert_eq!(Value::from(true).get(), ValueRef::Bool(true));
+            assert_eq!(Value::from("path").as_str(), Some("path"));
+            assert_eq!(Value::display(1.5).as_str(), Some("1.5"));
+        }
+
+        #[test]
+        fn fields_display() {
+            assert_eq!(Field::new("path", "/etc/app.toml").to_string(), "path=\"/etc/app.toml\"");
+            assert_eq!(Field::new("attempt", 3).to_string(), "attempt=3");
+        }
+    }
+}
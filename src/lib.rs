@@ -26,28 +26,51 @@
 #![cfg_attr(feature = "small-error", feature(extern_types, allocator_api))]
 
 macro_rules! with_std { ($($i:item)*) => ($(#[cfg(feature = "std")]$i)*) }
+#[allow(unused_macros)]
 macro_rules! without_std { ($($i:item)*) => ($(#[cfg(not(feature = "std"))]$i)*) }
+macro_rules! with_alloc { ($($i:item)*) => ($(#[cfg(feature = "alloc")]$i)*) }
+macro_rules! without_alloc { ($($i:item)*) => ($(#[cfg(not(feature = "alloc"))]$i)*) }
 
 // Re-export libcore using an alias so that the macros can work without
 // requiring `extern crate core` downstream.
 #[doc(hidden)]
 pub extern crate core as _core;
 
+// The types needing an allocator are used through `alloc`, which is the
+// `alloc` crate itself in `no_std` builds with the `alloc` feature.
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+#[macro_use]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std as alloc;
+
 mod as_fail;
 mod backtrace;
 #[cfg(feature = "std")]
 mod box_std;
 mod compat;
 mod context;
+mod explain;
+mod field;
+mod local;
+mod option_ext;
+mod report;
 mod result_ext;
 
-use core::any::TypeId;
+use core::any::{Any, TypeId};
 use core::fmt::{Debug, Display};
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
 
 pub use as_fail::AsFail;
 pub use backtrace::Backtrace;
 pub use compat::Compat;
-pub use context::Context;
+pub use context::{Context, LazyContext};
+pub use explain::{Explain, Explanation};
+pub use field::{Field, Value, ValueRef};
+pub use local::{LocalCauses, LocalFail};
+pub use option_ext::OptionExt;
+pub use report::JsonReport;
 pub use result_ext::ResultExt;
 
 #[cfg(feature = "failure_derive")]
@@ -64,19 +87,40 @@ with_std! {
 
     mod sync_failure;
     pub use sync_failure::SyncFailure;
+    pub use local::{LocalContext, LocalError, LocalStdError};
+    mod snapshot;
+    pub use snapshot::{Snapshot, SnapshotExt};
+    mod lock;
+    pub use lock::{LockExt, LockFailure, RwLockExt, SendFailure};
 
-    mod error;
+    pub use explain::Explanations;
 
     use std::error::Error as StdError;
 
+    mod cleanup;
+    pub use cleanup::CleanupGuard;
+    mod data_path;
+    pub use data_path::{DataPath, DataPathExt, PathContext, PathSegment};
+    mod retry;
+    pub use retry::{retry, Backoff, RetryHistory};
+    mod panic;
+    pub use panic::{catch_panic, install_panic_hook, JoinHandleExt, PanicFailure, PanicLocation};
+}
+
+with_alloc! {
+    mod error;
     pub use error::Error;
 
     /// A common result with an `Error`.
     pub type Fallible<T> = Result<T, Error>;
 
     mod macros;
+    #[doc(hidden)]
+    pub use macros::private as _private;
+    mod assertion;
+    pub use assertion::AssertionFailure;
     mod error_message;
-    pub use error_message::err_msg;
+    pub use error_message::{err_msg, ErrorMessage};
 }
 
 /// The `Fail` trait.
@@ -105,17 +149,54 @@ with_std! {
 /// your type. Additionally, all types that already implement
 /// `std::error::Error`, and are also `Send`, `Sync`, and `'static`, implement
 /// `Fail` by a blanket impl.
-pub trait Fail: Display + Debug + Send + Sync + 'static {
+pub trait Fail: Display + Debug + Send + Sync + 'static + local::AsLocalFail {
     /// Returns the "name" of the error.
-    /// 
+    ///
     /// This is typically the type name. Not all errors will implement
     /// this. This method is expected to be most useful in situations
-    /// where errors need to be reported to external instrumentation systems 
+    /// where errors need to be reported to external instrumentation systems
     /// such as crash reporters.
+    ///
+    /// Failures deriving `Fail` are named after their type, unless a stable
+    /// name is given with `#[fail(name = "...")]`. Types implementing
+    /// `std::error::Error` are named by `core::any::type_name`.
     fn name(&self) -> Option<&str> {
         None
     }
 
+    /// Returns the stable error code of this failure, if it has one.
+    ///
+    /// Error codes identify a failure in logs and reports independently of
+    /// its message, in the style of rustc's `E0308`. Failures deriving `Fail`
+    /// can declare one with `#[fail(code = "...")]`, and a long-form
+    /// explanation of each code can be looked up through `Explanations`. By
+    /// default, this returns `None`.
+    fn code(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns the structured fields carried by this failure.
+    ///
+    /// Fields are key-value data about the failure, kept apart from its
+    /// message so that reports and logs can render them as structured data.
+    /// A `Context` carries the fields attached with `Context::field`. By
+    /// default, this returns no fields.
+    fn fields(&self) -> &[Field] {
+        &[]
+    }
+
+    /// Returns whether this failure is transient, if it is classified.
+    ///
+    /// A transient failure, such as a timeout or a lost connection, may not
+    /// recur if the operation is retried, while a permanent failure, such as
+    /// invalid input, will. Failures deriving `Fail` can be classified with
+    /// `#[fail(transient)]` or `#[fail(permanent)]`. `Error::is_transient`
+    /// and `retry` use the first classified failure in the chain of an
+    /// error. By default, this returns `None`.
+    fn is_transient(&self) -> Option<bool> {
+        None
+    }
+
     /// Returns a reference to the underlying cause of this failure, if it
     /// is an error that wraps other errors.
     ///
@@ -174,7 +255,7 @@ pub trait Fail: Display + Debug + Send + Sync + 'static {
 
     #[doc(hidden)]
     #[deprecated(since = "0.1.2", note = "please use the 'iter_chain()' method instead")]
-    fn causes(&self) -> Causes
+    fn causes(&self) -> Causes<'_>
     where
         Self: Sized,
     {
@@ -197,6 +278,16 @@ pub trait Fail: Display + Debug + Send + Sync + 'static {
     fn __private_get_type_id__(&self) -> TypeId {
         TypeId::of::<Self>()
     }
+
+    #[doc(hidden)]
+    fn __private_get_kind__(&self) -> Option<&dyn Any> {
+        None
+    }
+
+    #[doc(hidden)]
+    fn __private_is_message__(&self) -> bool {
+        false
+    }
 }
 
 impl dyn Fail {
@@ -235,11 +326,38 @@ impl dyn Fail {
         find_root_cause(self)
     }
 
+    /// Returns the first error code found in the chain of this `Fail`,
+    /// starting with this failure itself.
+    pub fn find_code(&self) -> Option<&str> {
+        self.iter_chain().filter_map(|fail| fail.code()).next()
+    }
+
+    /// Returns whether the first classified failure in the chain of this
+    /// `Fail` is transient, starting with this failure itself.
+    ///
+    /// See `Fail::is_transient`.
+    pub fn find_transient(&self) -> Option<bool> {
+        self.iter_chain().filter_map(|fail| fail.is_transient()).next()
+    }
+
+    /// Returns the first kind of type `K` found in the chain of this `Fail`.
+    ///
+    /// Kinds are provided by failures deriving `Fail` with the
+    /// `#[fail(kind = "...")]` attribute, and by a `Context<K>`. The chain is
+    /// searched starting with this failure itself.
+    pub fn find_kind<K: Copy + 'static>(&self) -> Option<K> {
+        self.iter_chain()
+            .filter_map(|fail| fail.__private_get_kind__())
+            .filter_map(|kind| kind.downcast_ref::<K>())
+            .next()
+            .cloned()
+    }
+
     /// Returns a iterator over the causes of this `Fail` with the cause
     /// of this fail as the first item and the `root_cause` as the final item.
     ///
     /// Use `iter_chain` to also include the fail itself.
-    pub fn iter_causes(&self) -> Causes {
+    pub fn iter_causes(&self) -> Causes<'_> {
         Causes { fail: self.cause() }
     }
 
@@ -249,7 +367,7 @@ impl dyn Fail {
     /// This means that the chain also includes the fail itself which
     /// means that it does *not* start with `cause`.  To skip the outermost
     /// fail use `iter_causes` instead.
-    pub fn iter_chain(&self) -> Causes {
+    pub fn iter_chain(&self) -> Causes<'_> {
         Causes { fail: Some(self) }
     }
 
@@ -264,16 +382,36 @@ impl dyn Fail {
 
     /// Deprecated alias to `iter_chain`.
     #[deprecated(since = "0.1.2", note = "please use the 'iter_chain()' method instead")]
-    pub fn causes(&self) -> Causes {
+    pub fn causes(&self) -> Causes<'_> {
         Causes { fail: Some(self) }
     }
 }
 
 #[cfg(feature = "std")]
-impl<E: StdError + Send + Sync + 'static> Fail for E {}
+impl<E: StdError + Send + Sync + 'static> Fail for E {
+    fn name(&self) -> Option<&str> {
+        Some(core::any::type_name::<E>())
+    }
+}
 
-#[cfg(feature = "std")]
+#[cfg(feature = "alloc")]
 impl Fail for Box<dyn Fail> {
+    fn name(&self) -> Option<&str> {
+        (**self).name()
+    }
+
+    fn code(&self) -> Option<&str> {
+        (**self).code()
+    }
+
+    fn fields(&self) -> &[Field] {
+        (**self).fields()
+    }
+
+    fn is_transient(&self) -> Option<bool> {
+        (**self).is_transient()
+    }
+
     fn cause(&self) -> Option<&dyn Fail> {
         (**self).cause()
     }
@@ -281,6 +419,14 @@ impl Fail for Box<dyn Fail> {
     fn backtrace(&self) -> Option<&Backtrace> {
         (**self).backtrace()
     }
+
+    fn __private_get_kind__(&self) -> Option<&dyn Any> {
+        (**self).__private_get_kind__()
+    }
+
+    fn __private_is_message__(&self) -> bool {
+        (**self).__private_is_message__()
+    }
 }
 
 /// A iterator over the causes of a `Fail`
@@ -291,10 +437,9 @@ pub struct Causes<'f> {
 impl<'f> Iterator for Causes<'f> {
     type Item = &'f dyn Fail;
     fn next(&mut self) -> Option<&'f dyn Fail> {
-        self.fail.map(|fail| {
-            self.fail = fail.cause();
-            fail
-        })
+        let fail = self.fail?;
+        self.fail = fail.cause();
+        Some(fail)
     }
 }
 
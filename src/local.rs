@@ -0,0 +1,455 @@
+use core::any::TypeId;
+use core::fmt::{self, Debug, Display};
+
+use {Backtrace, Fail};
+
+/// A failure which need not be `Send` or `Sync`.
+///
+/// `LocalFail` mirrors `Fail` for errors which can never leave the thread
+/// they were created on, such as errors holding an `Rc`. Its methods have
+/// the same meaning as those of `Fail`, and `LocalError` mirrors `Error`.
+/// They are prefixed with `local_`, so that they are not ambiguous with the
+/// methods of `Fail` for types implementing both traits.
+///
+/// A `Fail` joins a chain of local failures as an `Error`, which is a
+/// `LocalFail` whose causes are the causes of its failure. The failures
+/// below an `Error` can be downcast to their `Fail` types.
+pub trait LocalFail: Display + Debug + 'static {
+    /// Returns the "name" of the error. See `Fail::name`.
+    fn local_name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns a reference to the underlying cause of this failure, if it
+    /// is an error that wraps other errors. See `Fail::cause`.
+    fn local_cause(&self) -> Option<&dyn LocalFail> {
+        None
+    }
+
+    /// Returns a reference to the `Backtrace` carried by this failure, if it
+    /// carries one. See `Fail::backtrace`.
+    fn local_backtrace(&self) -> Option<&Backtrace> {
+        None
+    }
+
+    /// Provides context for this failure. See `Fail::context`.
+    #[cfg(feature = "std")]
+    fn local_context<D>(self, context: D) -> ::LocalContext<D>
+    where
+        D: Display + 'static,
+        Self: Sized,
+    {
+        ::LocalContext::with_err(context, self)
+    }
+
+    #[doc(hidden)]
+    fn __private_get_type_id__(&self) -> TypeId {
+        TypeId::of::<Self>()
+    }
+
+    // Returns an `Error` as itself, so that a `LocalError` made from it can
+    // be converted back into an `Error`.
+    #[doc(hidden)]
+    #[cfg(feature = "std")]
+    fn __private_into_error__(self) -> Result<::Error, Self>
+    where
+        Self: Sized,
+    {
+        Err(self)
+    }
+}
+
+/// Views a `Fail` as a `LocalFail`, so that the chain of an `Error` can be
+/// followed from a chain of local failures.
+#[doc(hidden)]
+pub trait AsLocalFail {
+    fn __private_as_local_fail__(&self) -> &dyn LocalFail;
+}
+
+impl<F: Fail> AsLocalFail for F {
+    fn __private_as_local_fail__(&self) -> &dyn LocalFail {
+        unsafe { &*(self as *const F as *const FailView<F>) }
+    }
+}
+
+// A `Fail` viewed as a `LocalFail`, laid out as the `Fail` itself, so that
+// it can be downcast to the type of the `Fail`.
+#[repr(transparent)]
+struct FailView<F>(F);
+
+impl<F: Fail> LocalFail for FailView<F> {
+    fn local_name(&self) -> Option<&str> {
+        self.0.name()
+    }
+
+    fn local_cause(&self) -> Option<&dyn LocalFail> {
+        self.0.cause().map(AsLocalFail::__private_as_local_fail__)
+    }
+
+    fn local_backtrace(&self) -> Option<&Backtrace> {
+        self.0.backtrace()
+    }
+
+    fn __private_get_type_id__(&self) -> TypeId {
+        self.0.__private_get_type_id__()
+    }
+}
+
+impl<F: Display> Display for FailView<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl<F: Debug> Debug for FailView<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+impl dyn LocalFail {
+    /// Attempts to downcast this failure to a concrete type by reference.
+    ///
+    /// The failures in the chain of an `Error` can be downcast to their
+    /// `Fail` types.
+    pub fn downcast_ref<T: Display + Debug + 'static>(&self) -> Option<&T> {
+        if self.__private_get_type_id__() == TypeId::of::<T>() {
+            unsafe { Some(&*(self as *const dyn LocalFail as *const T)) }
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to downcast this failure to a concrete type by mutable
+    /// reference.
+    pub fn downcast_mut<T: LocalFail>(&mut self) -> Option<&mut T> {
+        if self.__private_get_type_id__() == TypeId::of::<T>() {
+            unsafe { Some(&mut *(self as *mut dyn LocalFail as *mut T)) }
+        } else {
+            None
+        }
+    }
+
+    /// Returns the last failure in the chain of causes of this failure.
+    pub fn find_root_cause(&self) -> &dyn LocalFail {
+        self.iter_chain().last().unwrap_or(self)
+    }
+
+    /// Returns an iterator over the causes of this failure, not including
+    /// the failure itself.
+    pub fn iter_causes(&self) -> LocalCauses<'_> {
+        LocalCauses { fail: self.local_cause() }
+    }
+
+    /// Returns an iterator over this failure and its causes.
+    pub fn iter_chain(&self) -> LocalCauses<'_> {
+        LocalCauses { fail: Some(self) }
+    }
+}
+
+/// An iterator over the causes of a `LocalFail`.
+pub struct LocalCauses<'f> {
+    fail: Option<&'f dyn LocalFail>,
+}
+
+impl<'f> Iterator for LocalCauses<'f> {
+    type Item = &'f dyn LocalFail;
+    fn next(&mut self) -> Option<&'f dyn LocalFail> {
+        let fail = self.fail?;
+        self.fail = fail.local_cause();
+        Some(fail)
+    }
+}
+
+with_std! {
+    use core::any::Any;
+    use std::error::Error as StdError;
+
+    use Error;
+
+    // The chain of the `Error` continues with the causes of its failure,
+    // viewed as `LocalFail`s.
+    impl LocalFail for Error {
+        fn local_name(&self) -> Option<&str> {
+            self.name()
+        }
+
+        fn local_cause(&self) -> Option<&dyn LocalFail> {
+            self.as_fail().cause().map(AsLocalFail::__private_as_local_fail__)
+        }
+
+        fn local_backtrace(&self) -> Option<&Backtrace> {
+            Some(self.backtrace())
+        }
+
+        fn __private_into_error__(self) -> Result<Error, Error> {
+            Ok(self)
+        }
+    }
+
+    /// The `Error` type for failures which need not be `Send` or `Sync`.
+    ///
+    /// `LocalError` mirrors `Error`: any `LocalFail` can be converted into
+    /// it, it carries a backtrace, and it can be downcast. An `Error` can
+    /// be converted into a `LocalError` with `From`, and converted back
+    /// with `into_error`, as long as no local failure was added to it.
+    /// Failures which are not `LocalFail`s are converted into an `Error`
+    /// first.
+    pub struct LocalError {
+        repr: Repr,
+    }
+
+    enum Repr {
+        Local(Box<Inner<dyn LocalFail>>),
+        Shared(Error),
+    }
+
+    struct Inner<F: ?Sized + LocalFail> {
+        backtrace: Backtrace,
+        failure: F,
+    }
+
+    impl<F: LocalFail> From<F> for LocalError {
+        fn from(failure: F) -> LocalError {
+            let failure = match failure.__private_into_error__() {
+                Ok(error) => return LocalError { repr: Repr::Shared(error) },
+                Err(failure) => failure,
+            };
+            let backtrace = if failure.local_backtrace().is_none() {
+                Backtrace::new()
+            } else {
+                Backtrace::none()
+            };
+            LocalError { repr: Repr::Local(Box::new(Inner { backtrace, failure })) }
+        }
+    }
+
+    /// A `std::error::Error` which is not `Send` or `Sync`, as a
+    /// `LocalFail`.
+    ///
+    /// This is for errors defined in other crates, which cannot implement
+    /// `LocalFail`. As with the blanket impl of `Fail` for
+    /// `std::error::Error`, the `source` of the error is not part of its
+    /// chain.
+    pub struct LocalStdError<E> {
+        error: E,
+    }
+
+    impl<E: StdError + 'static> LocalStdError<E> {
+        /// Wraps an error.
+        pub fn new(error: E) -> LocalStdError<E> {
+            LocalStdError { error }
+        }
+
+        /// Returns a reference to the wrapped error.
+        pub fn get_ref(&self) -> &E {
+            &self.error
+        }
+
+        /// Unwraps the wrapped error.
+        pub fn into_inner(self) -> E {
+            self.error
+        }
+    }
+
+    impl<E: Display> Display for LocalStdError<E> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            Display::fmt(&self.error, f)
+        }
+    }
+
+    impl<E: Debug> Debug for LocalStdError<E> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            Debug::fmt(&self.error, f)
+        }
+    }
+
+    impl<E: StdError + 'static> LocalFail for LocalStdError<E> {
+        fn local_name(&self) -> Option<&str> {
+            Some(::core::any::type_name::<E>())
+        }
+    }
+
+    impl LocalError {
+        /// Creates a `LocalError` from a `std::error::Error` which is not
+        /// `Send` or `Sync`, by wrapping it in a `LocalStdError`.
+        pub fn from_std<E: StdError + 'static>(error: E) -> LocalError {
+            LocalError::from(LocalStdError::new(error))
+        }
+
+        /// Converts this back into an `Error`, if it was made from an
+        /// `Error` or a `Fail`, and no local failure has been added to it
+        /// since.
+        pub fn into_error(self) -> Result<Error, LocalError> {
+            match self.repr {
+                Repr::Shared(error) => Ok(error),
+                repr => Err(LocalError { repr }),
+            }
+        }
+
+        /// Returns a reference to the underlying failure.
+        pub fn as_fail(&self) -> &dyn LocalFail {
+            match self.repr {
+                Repr::Local(ref inner) => &inner.failure,
+                Repr::Shared(ref error) => error,
+            }
+        }
+
+        /// Returns the name of the underlying failure.
+        pub fn name(&self) -> Option<&str> {
+            self.as_fail().local_name()
+        }
+
+        /// Gets a reference to the `Backtrace` for this error. See
+        /// `Error::backtrace`.
+        pub fn backtrace(&self) -> &Backtrace {
+            match self.repr {
+                Repr::Local(ref inner) => inner.failure.local_backtrace().unwrap_or(&inner.backtrace),
+                Repr::Shared(ref error) => error.backtrace(),
+            }
+        }
+
+        /// Provides context for this error. See `Error::context`.
+        pub fn context<D: Display + 'static>(self, context: D) -> LocalContext<D> {
+            LocalContext::with_err(context, self)
+        }
+
+        /// Attempts to downcast this error to a particular `LocalFail` type
+        /// by value.
+        pub fn downcast<T: LocalFail>(self) -> Result<T, LocalError> {
+            match self.repr {
+                Repr::Shared(error) => {
+                    // Only the `Error` itself can be taken by value.
+                    let mut error = Some(error);
+                    match (&mut error as &mut dyn Any).downcast_mut::<Option<T>>() {
+                        Some(failure) => Ok(failure.take().expect("error was taken")),
+                        None => Err(LocalError::from(error.expect("error was taken"))),
+                    }
+                }
+                Repr::Local(inner) => {
+                    if inner.failure.__private_get_type_id__() != TypeId::of::<T>() {
+                        return Err(LocalError { repr: Repr::Local(inner) });
+                    }
+                    let inner = Box::into_raw(inner);
+                    let casted = unsafe { Box::from_raw(inner as *mut Inner<T>) };
+                    let Inner { backtrace: _, failure } = *casted;
+                    Ok(failure)
+                }
+            }
+        }
+
+        /// Attempts to downcast this error to a particular `LocalFail` type
+        /// by reference, or to the `Error` it was made from.
+        pub fn downcast_ref<T: Display + Debug + 'static>(&self) -> Option<&T> {
+            self.as_fail().downcast_ref()
+        }
+
+        /// Attempts to downcast this error to a particular `LocalFail` type
+        /// by mutable reference.
+        pub fn downcast_mut<T: LocalFail>(&mut self) -> Option<&mut T> {
+            let fail = match self.repr {
+                Repr::Local(ref mut inner) => &mut inner.failure,
+                Repr::Shared(ref mut error) => error,
+            };
+            fail.downcast_mut()
+        }
+
+        /// Returns the last failure in the chain of causes of this error.
+        pub fn find_root_cause(&self) -> &dyn LocalFail {
+            self.as_fail().find_root_cause()
+        }
+
+        /// Returns an iterator over the causes of this error, not including
+        /// the underlying failure itself.
+        pub fn iter_causes(&self) -> LocalCauses<'_> {
+            self.as_fail().iter_causes()
+        }
+
+        /// Returns an iterator over the underlying failure and its causes.
+        pub fn iter_chain(&self) -> LocalCauses<'_> {
+            self.as_fail().iter_chain()
+        }
+    }
+
+    impl Display for LocalError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            Display::fmt(self.as_fail(), f)
+        }
+    }
+
+    impl Debug for LocalError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self.repr {
+                Repr::Shared(ref error) => Debug::fmt(error, f),
+                Repr::Local(ref inner) if inner.backtrace.is_none() => {
+                    Debug::fmt(&inner.failure, f)
+                }
+                Repr::Local(ref inner) => {
+                    write!(f, "{:?}\n\n{:?}", &inner.failure, inner.backtrace)
+                }
+            }
+        }
+    }
+
+    /// A local failure with context around it, mirroring `Context`.
+    pub struct LocalContext<D: Display + 'static> {
+        context: D,
+        failure: Either,
+    }
+
+    enum Either {
+        This(Backtrace),
+        That(LocalError),
+    }
+
+    impl<D: Display + 'static> LocalContext<D> {
+        /// Creates a new context without an underlying error message.
+        pub fn new(context: D) -> LocalContext<D> {
+            LocalContext { context, failure: Either::This(Backtrace::new()) }
+        }
+
+        /// Returns a reference to the context provided with this error.
+        pub fn get_context(&self) -> &D {
+            &self.context
+        }
+
+        pub(crate) fn with_err<E: Into<LocalError>>(context: D, error: E) -> LocalContext<D> {
+            LocalContext { context, failure: Either::That(error.into()) }
+        }
+    }
+
+    impl<D: Display + 'static> LocalFail for LocalContext<D> {
+        fn local_name(&self) -> Option<&str> {
+            Some("failure::LocalContext")
+        }
+
+        fn local_cause(&self) -> Option<&dyn LocalFail> {
+            match self.failure {
+                Either::This(_) => None,
+                Either::That(ref error) => Some(error.as_fail()),
+            }
+        }
+
+        fn local_backtrace(&self) -> Option<&Backtrace> {
+            Some(match self.failure {
+                Either::This(ref backtrace) => backtrace,
+                Either::That(ref error) => error.backtrace(),
+            })
+        }
+    }
+
+    impl<D: Display + 'static> Debug for LocalContext<D> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self.failure {
+                Either::This(ref backtrace) => write!(f, "{:?}\n\n{}", backtrace, self.context),
+                Either::That(ref error) => write!(f, "{:?}\n\n{}", error, self.context),
+            }
+        }
+    }
+
+    impl<D: Display + 'static> Display for LocalContext<D> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            Display::fmt(&self.context, f)
+        }
+    }
+}
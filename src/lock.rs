@@ -0,0 +1,146 @@
+use std::fmt::{self, Display};
+use std::sync::mpsc::{SendError, TrySendError};
+use std::sync::{Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::TryLockError;
+
+use Fail;
+
+/// The failure to acquire a lock, without the guard carried by
+/// `PoisonError` and `TryLockError`, which is neither `Send` nor `'static`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LockFailure {
+    /// Another thread panicked while holding the lock.
+    Poisoned,
+    /// The lock could not be acquired without blocking.
+    WouldBlock,
+}
+
+impl<T> From<PoisonError<T>> for LockFailure {
+    fn from(_: PoisonError<T>) -> LockFailure {
+        LockFailure::Poisoned
+    }
+}
+
+impl<T> From<TryLockError<T>> for LockFailure {
+    fn from(error: TryLockError<T>) -> LockFailure {
+        match error {
+            TryLockError::Poisoned(_) => LockFailure::Poisoned,
+            TryLockError::WouldBlock => LockFailure::WouldBlock,
+        }
+    }
+}
+
+impl Display for LockFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LockFailure::Poisoned => write!(f, "poisoned lock: another task failed inside"),
+            LockFailure::WouldBlock => {
+                write!(f, "try_lock failed because the operation would block")
+            }
+        }
+    }
+}
+
+impl Fail for LockFailure {
+    fn is_transient(&self) -> Option<bool> {
+        Some(*self == LockFailure::WouldBlock)
+    }
+}
+
+/// The failure to send a value on a channel, without the value carried by
+/// `SendError` and `TrySendError`, which need not be `Send` or `Sync`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SendFailure {
+    /// The channel is full.
+    Full,
+    /// The receiving half of the channel was dropped.
+    Disconnected,
+}
+
+impl<T> From<SendError<T>> for SendFailure {
+    fn from(_: SendError<T>) -> SendFailure {
+        SendFailure::Disconnected
+    }
+}
+
+impl<T> From<TrySendError<T>> for SendFailure {
+    fn from(error: TrySendError<T>) -> SendFailure {
+        match error {
+            TrySendError::Full(_) => SendFailure::Full,
+            TrySendError::Disconnected(_) => SendFailure::Disconnected,
+        }
+    }
+}
+
+impl Display for SendFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SendFailure::Full => write!(f, "sending on a full channel"),
+            SendFailure::Disconnected => write!(f, "sending on a closed channel"),
+        }
+    }
+}
+
+impl Fail for SendFailure {
+    fn is_transient(&self) -> Option<bool> {
+        Some(*self == SendFailure::Full)
+    }
+}
+
+/// Extension methods for `Mutex`, returning a `LockFailure` instead of a
+/// `PoisonError`.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Mutex;
+/// use failure::{Error, LockExt};
+///
+/// fn increment(counter: &Mutex<u32>) -> Result<u32, Error> {
+///     let mut counter = counter.lock_fallible()?;
+///     *counter += 1;
+///     Ok(*counter)
+/// }
+///
+/// assert_eq!(increment(&Mutex::new(1)).unwrap(), 2);
+/// ```
+pub trait LockExt<T: ?Sized> {
+    /// Acquires the mutex, blocking the current thread until it is able to
+    /// do so.
+    fn lock_fallible(&self) -> Result<MutexGuard<'_, T>, LockFailure>;
+
+    /// Attempts to acquire the mutex without blocking.
+    fn try_lock_fallible(&self) -> Result<MutexGuard<'_, T>, LockFailure>;
+}
+
+impl<T: ?Sized> LockExt<T> for Mutex<T> {
+    fn lock_fallible(&self) -> Result<MutexGuard<'_, T>, LockFailure> {
+        Ok(self.lock()?)
+    }
+
+    fn try_lock_fallible(&self) -> Result<MutexGuard<'_, T>, LockFailure> {
+        Ok(self.try_lock()?)
+    }
+}
+
+/// Extension methods for `RwLock`, returning a `LockFailure` instead of a
+/// `PoisonError`.
+pub trait RwLockExt<T: ?Sized> {
+    /// Locks the lock with shared read access, blocking the current thread
+    /// until it can be acquired.
+    fn read_fallible(&self) -> Result<RwLockReadGuard<'_, T>, LockFailure>;
+
+    /// Locks the lock with exclusive write access, blocking the current
+    /// thread until it can be acquired.
+    fn write_fallible(&self) -> Result<RwLockWriteGuard<'_, T>, LockFailure>;
+}
+
+impl<T: ?Sized> RwLockExt<T> for RwLock<T> {
+    fn read_fallible(&self) -> Result<RwLockReadGuard<'_, T>, LockFailure> {
+        Ok(self.read()?)
+    }
+
+    fn write_fallible(&self) -> Result<RwLockWriteGuard<'_, T>, LockFailure> {
+        Ok(self.write()?)
+    }
+}
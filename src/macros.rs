@@ -1,33 +1,44 @@
-/// Exits a function early with an `Error`.
+/// Exits a function early with an error.
 ///
-/// The `bail!` macro provides an easy way to exit a function. `bail!(X)` is
-/// equivalent to writing:
+/// The `bail!` macro provides an easy way to exit a function. A failure, or an
+/// `Error`, is returned as itself, converted with `Into` to the error type of
+/// the function, so `bail!(X)` is equivalent to writing:
+///
+/// ```rust,ignore
+/// return Err(X.into())
+/// ```
+///
+/// This also works in functions returning a `Result` of a concrete failure
+/// type. Any other value is treated as an error message, in which case
+/// `bail!(X)` is equivalent to writing:
 ///
 /// ```rust,ignore
 /// return Err(format_err!(X))
 /// ```
-#[macro_export]
+#[macro_export(local_inner_macros)]
 macro_rules! bail {
     ($e:expr) => {
-        return Err($crate::err_msg($e));
+        return Err({
+            #[allow(unused_imports)]
+            use $crate::_private::{FailKind, MessageKind};
+            match $e {
+                error => (&error).bail_kind().into_error(error),
+            }
+        });
     };
     ($fmt:expr, $($arg:tt)*) => {
-        return Err($crate::err_msg(format!($fmt, $($arg)*)));
+        return Err(format_err!($fmt, $($arg)*));
     };
 }
 
-/// Exits a function early with an `Error` if the condition is not satisfied.
+/// Exits a function early with an error if the condition is not satisfied.
 ///
 /// Similar to `assert!`, `ensure!` takes a condition and exits the function
-/// if the condition fails. Unlike `assert!`, `ensure!` returns an `Error`,
-/// it does not panic.
+/// if the condition fails. Unlike `assert!`, `ensure!` returns an error,
+/// it does not panic. As with `bail!`, the error can be a failure, which is
+/// returned as itself, or a message.
 #[macro_export(local_inner_macros)]
 macro_rules! ensure {
-    ($cond:expr) => {
-        if !($cond) {
-            bail!("{}", _failure__stringify!($cond));
-        }
-    };
     ($cond:expr, $e:expr) => {
         if !($cond) {
             bail!($e);
@@ -38,6 +49,178 @@ macro_rules! ensure {
             bail!($fmt, $($arg)*);
         }
     };
+    ($($cond:tt)+) => {
+        _failure__ensure_cmp!(@scan ($($cond)+) $($cond)+)
+    };
+}
+
+/// Exits a function early with an `AssertionFailure` if two expressions are
+/// not equal.
+///
+/// Like `assert_eq!`, the failure carries the `Debug` representation of both
+/// operands. A custom message can be given after the operands. Like
+/// `ensure!`, this returns an error instead of panicking.
+#[macro_export]
+macro_rules! ensure_eq {
+    ($left:expr, $right:expr $(,)*) => {
+        $crate::_failure__ensure_cmp!(@check
+            concat!(stringify!($left), " == ", stringify!($right)),
+            $left, ==, $right, $crate::_core::option::Option::None)
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        $crate::_failure__ensure_cmp!(@check
+            concat!(stringify!($left), " == ", stringify!($right)),
+            $left, ==, $right, $crate::_core::option::Option::Some($crate::_private::format(format_args!($($arg)+))))
+    };
+}
+
+/// Exits a function early with an `AssertionFailure` if two expressions are
+/// equal.
+///
+/// See `ensure_eq!`.
+#[macro_export]
+macro_rules! ensure_ne {
+    ($left:expr, $right:expr $(,)*) => {
+        $crate::_failure__ensure_cmp!(@check
+            concat!(stringify!($left), " != ", stringify!($right)),
+            $left, !=, $right, $crate::_core::option::Option::None)
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        $crate::_failure__ensure_cmp!(@check
+            concat!(stringify!($left), " != ", stringify!($right)),
+            $left, !=, $right, $crate::_core::option::Option::Some($crate::_private::format(format_args!($($arg)+))))
+    };
+}
+
+/// Exits a function early with an `AssertionFailure` if an expression does
+/// not match a pattern.
+///
+/// The pattern can have an `if` guard, as in a `match` arm. The failure
+/// carries the `Debug` representation of the value as its left operand.
+///
+/// ```rust
+/// #[macro_use] extern crate failure;
+///
+/// # use failure::Error;
+/// fn parse_port(port: &str) -> Result<u16, Error> {
+///     let port = port.parse::<u16>();
+///     ensure_matches!(port, Ok(p) if p >= 1024, "ports below 1024 are reserved");
+///     Ok(port?)
+/// }
+/// # fn main() { assert!(parse_port("80").is_err()); }
+/// ```
+#[macro_export]
+macro_rules! ensure_matches {
+    ($e:expr, $pat:pat $(,)*) => {
+        $crate::_failure__ensure_cmp!(@matches $e, $pat, true,
+            concat!(stringify!($e), " matches ", stringify!($pat)),
+            $crate::_core::option::Option::None)
+    };
+    ($e:expr, $pat:pat if $guard:expr $(,)*) => {
+        $crate::_failure__ensure_cmp!(@matches $e, $pat, $guard,
+            concat!(stringify!($e), " matches ", stringify!($pat), " if ", stringify!($guard)),
+            $crate::_core::option::Option::None)
+    };
+    ($e:expr, $pat:pat, $($arg:tt)+) => {
+        $crate::_failure__ensure_cmp!(@matches $e, $pat, true,
+            concat!(stringify!($e), " matches ", stringify!($pat)),
+            $crate::_core::option::Option::Some($crate::_private::format(format_args!($($arg)+))))
+    };
+    ($e:expr, $pat:pat if $guard:expr, $($arg:tt)+) => {
+        $crate::_failure__ensure_cmp!(@matches $e, $pat, $guard,
+            concat!(stringify!($e), " matches ", stringify!($pat), " if ", stringify!($guard)),
+            $crate::_core::option::Option::Some($crate::_private::format(format_args!($($arg)+))))
+    };
+}
+
+// Implements the checks of `ensure!` and the `ensure_*!` macros.
+//
+// `ensure!` scans its condition for a top-level comparison to split into
+// operands. Conditions with operators binding more loosely than comparisons,
+// closures, casts or generic arguments are not split, and fail with the
+// condition as a message as before.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _failure__ensure_cmp {
+    (@scan $cond:tt && $($rest:tt)*) => { $crate::_failure__ensure_cmp!(@plain $cond) };
+    (@scan $cond:tt || $($rest:tt)*) => { $crate::_failure__ensure_cmp!(@plain $cond) };
+    (@scan $cond:tt .. $($rest:tt)*) => { $crate::_failure__ensure_cmp!(@plain $cond) };
+    (@scan $cond:tt ..= $($rest:tt)*) => { $crate::_failure__ensure_cmp!(@plain $cond) };
+    (@scan $cond:tt | $($rest:tt)*) => { $crate::_failure__ensure_cmp!(@plain $cond) };
+    (@scan $cond:tt as $($rest:tt)*) => { $crate::_failure__ensure_cmp!(@plain $cond) };
+    (@scan $cond:tt :: < $($rest:tt)*) => { $crate::_failure__ensure_cmp!(@plain $cond) };
+    (@scan $cond:tt $head:tt $($rest:tt)*) => { $crate::_failure__ensure_cmp!(@scan $cond $($rest)*) };
+    (@scan ($($cond:tt)*)) => { $crate::_failure__ensure_cmp!(@split ($($cond)*) [] $($cond)*) };
+
+    (@split $cond:tt [$($left:tt)+] == $($right:tt)+) => {
+        $crate::_failure__ensure_cmp!(@split_at $cond [$($left)+] == [$($right)+])
+    };
+    (@split $cond:tt [$($left:tt)+] != $($right:tt)+) => {
+        $crate::_failure__ensure_cmp!(@split_at $cond [$($left)+] != [$($right)+])
+    };
+    (@split $cond:tt [$($left:tt)+] <= $($right:tt)+) => {
+        $crate::_failure__ensure_cmp!(@split_at $cond [$($left)+] <= [$($right)+])
+    };
+    (@split $cond:tt [$($left:tt)+] >= $($right:tt)+) => {
+        $crate::_failure__ensure_cmp!(@split_at $cond [$($left)+] >= [$($right)+])
+    };
+    (@split $cond:tt [$($left:tt)+] < $($right:tt)+) => {
+        $crate::_failure__ensure_cmp!(@split_at $cond [$($left)+] < [$($right)+])
+    };
+    (@split $cond:tt [$($left:tt)+] > $($right:tt)+) => {
+        $crate::_failure__ensure_cmp!(@split_at $cond [$($left)+] > [$($right)+])
+    };
+    (@split $cond:tt [$($left:tt)*] $head:tt $($rest:tt)*) => {
+        $crate::_failure__ensure_cmp!(@split $cond [$($left)* $head] $($rest)*)
+    };
+    (@split $cond:tt [$($left:tt)*]) => { $crate::_failure__ensure_cmp!(@plain $cond) };
+
+    (@split_at ($($cond:tt)*) [$($left:tt)+] $op:tt [$($right:tt)+]) => {
+        $crate::_failure__ensure_cmp!(@check
+            stringify!($($cond)*),
+            ($($left)+), $op, ($($right)+), $crate::_core::option::Option::None)
+    };
+
+    (@plain ($($cond:tt)*)) => {
+        if !($($cond)*) {
+            $crate::bail!(stringify!($($cond)*));
+        }
+    };
+
+    (@check $expr:expr, $left:expr, $op:tt, $right:expr, $message:expr) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if !(*left $op *right) {
+                    #[allow(unused_imports)]
+                    use $crate::_private::{DebugOperand, OpaqueOperand};
+                    $crate::bail!($crate::AssertionFailure::_new(
+                        $expr,
+                        (&$crate::_private::Operand(left)).operand_repr(),
+                        (&$crate::_private::Operand(right)).operand_repr(),
+                        $message,
+                        (file!(), line!(), column!()),
+                    ));
+                }
+            }
+        }
+    };
+
+    (@matches $e:expr, $pat:pat, $guard:expr, $expr:expr, $message:expr) => {
+        match $e {
+            $pat if $guard => {}
+            ref value => {
+                #[allow(unused_imports)]
+                use $crate::_private::{DebugOperand, OpaqueOperand};
+                $crate::bail!($crate::AssertionFailure::_new(
+                    $expr,
+                    (&$crate::_private::Operand(value)).operand_repr(),
+                    $crate::_core::option::Option::None,
+                    $message,
+                    (file!(), line!(), column!()),
+                ));
+            }
+        }
+    };
 }
 
 #[doc(hidden)]
@@ -50,6 +233,9 @@ macro_rules! _failure__stringify {
 
 /// Constructs an `Error` using the standard string interpolation syntax.
 ///
+/// The `Error` contains an `ErrorMessage`. A message without any arguments to
+/// interpolate is not copied into a `String`.
+///
 /// ```rust
 /// #[macro_use] extern crate failure;
 ///
@@ -60,5 +246,95 @@ macro_rules! _failure__stringify {
 /// ```
 #[macro_export]
 macro_rules! format_err {
-    ($($arg:tt)*) => { $crate::err_msg(format!($($arg)*)) }
+    ($msg:literal) => {
+        $crate::_private::literal_message($msg, format_args!($msg))
+    };
+    ($($arg:tt)*) => {
+        $crate::err_msg($crate::_private::format(format_args!($($arg)*)))
+    };
+}
+
+// Support for the macros, which use autoref-based dispatch to pick an
+// implementation by the traits a type implements.
+//
+// For `bail!`, `FailKind` applies to `&E` directly when `E` is a failure or an
+// `Error`, and `MessageKind` applies only after another autoref, to anything
+// else that can be displayed. Likewise, operands of failed checks are
+// formatted by `DebugOperand` when they implement `Debug`, and are left out by
+// `OpaqueOperand` otherwise.
+#[doc(hidden)]
+pub mod private {
+    use core::fmt::{self, Debug, Display};
+    use alloc::string::String;
+
+    use {err_msg, Error, Fail};
+
+    // Formats the messages of the macros, which cannot name `format!` in
+    // `no_std` crates.
+    pub fn format(args: fmt::Arguments) -> String {
+        ::alloc::fmt::format(args)
+    }
+
+    // A literal without braces is its own message, and is not copied.
+    pub fn literal_message(msg: &'static str, args: fmt::Arguments) -> Error {
+        if msg.contains('{') || msg.contains('}') {
+            err_msg(format(args))
+        } else {
+            err_msg(msg)
+        }
+    }
+
+    pub struct Failure;
+
+    impl Failure {
+        pub fn into_error<F: Into<E>, E>(self, failure: F) -> E {
+            failure.into()
+        }
+    }
+
+    pub struct Message;
+
+    impl Message {
+        pub fn into_error<D: Display + Debug + Sync + Send + 'static>(self, msg: D) -> Error {
+            err_msg(msg)
+        }
+    }
+
+    pub trait FailKind {
+        fn bail_kind(&self) -> Failure {
+            Failure
+        }
+    }
+
+    impl<F: Fail> FailKind for F {}
+
+    impl FailKind for Error {}
+
+    pub trait MessageKind {
+        fn bail_kind(&self) -> Message {
+            Message
+        }
+    }
+
+    impl<D: Display + Debug + Sync + Send + 'static> MessageKind for &D {}
+
+    pub struct Operand<'a, T: ?Sized + 'a>(pub &'a T);
+
+    pub trait DebugOperand {
+        fn operand_repr(&self) -> Option<String>;
+    }
+
+    impl<'a, T: Debug + ?Sized> DebugOperand for Operand<'a, T> {
+        fn operand_repr(&self) -> Option<String> {
+            Some(format!("{:?}", self.0))
+        }
+    }
+
+    pub trait OpaqueOperand {
+        fn operand_repr(&self) -> Option<String> {
+            None
+        }
+    }
+
+    impl<'a, T: ?Sized> OpaqueOperand for &Operand<'a, T> {}
 }
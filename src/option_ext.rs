@@ -0,0 +1,93 @@
+use core::fmt::Display;
+
+use {Context, Fail};
+
+/// Extension methods for `Option`, to turn `None` into a failure.
+pub trait OptionExt<T> {
+    /// Converts `None` into a context type, with no underlying error.
+    ///
+    /// As with `Context::new`, the context captures a backtrace.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use failure::OptionExt;
+    ///
+    /// let port = None::<u16>.context("no port configured").unwrap_err();
+    /// assert_eq!(port.to_string(), "no port configured");
+    /// ```
+    fn context<D>(self, context: D) -> Result<T, Context<D>>
+    where
+        D: Display + Send + Sync + 'static;
+
+    /// Converts `None` into a context type generated by a closure, which is
+    /// only called if the option is `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use failure::OptionExt;
+    ///
+    /// let name = "PORT";
+    /// let err = None::<u16>.with_context(|| format!("{} is not set", name)).unwrap_err();
+    /// assert_eq!(err.to_string(), "PORT is not set");
+    /// ```
+    fn with_context<F, D>(self, f: F) -> Result<T, Context<D>>
+    where
+        F: FnOnce() -> D,
+        D: Display + Send + Sync + 'static;
+
+    /// Converts `None` into the given failure.
+    fn ok_or_fail<F: Fail>(self, failure: F) -> Result<T, F>;
+}
+
+impl<T> OptionExt<T> for Option<T> {
+    fn context<D>(self, context: D) -> Result<T, Context<D>>
+    where
+        D: Display + Send + Sync + 'static,
+    {
+        self.ok_or_else(|| Context::new(context))
+    }
+
+    fn with_context<F, D>(self, f: F) -> Result<T, Context<D>>
+    where
+        F: FnOnce() -> D,
+        D: Display + Send + Sync + 'static,
+    {
+        self.ok_or_else(|| Context::new(f()))
+    }
+
+    fn ok_or_fail<F: Fail>(self, failure: F) -> Result<T, F> {
+        self.ok_or(failure)
+    }
+}
+
+with_std! {
+    #[cfg(test)]
+    mod test {
+        use std::io;
+
+        use super::OptionExt;
+        use Fail;
+
+        #[test]
+        fn some_is_kept() {
+            assert_eq!(Some(4).context("missing").unwrap(), 4);
+            assert_eq!(Some(4).with_context(|| -> &str { unreachable!() }).unwrap(), 4);
+        }
+
+        #[test]
+        fn none_becomes_context() {
+            let err = None::<u32>.context("missing value").unwrap_err();
+            assert_eq!(err.to_string(), "missing value");
+            assert!(err.cause().is_none());
+            assert!(err.backtrace().is_some());
+        }
+
+        #[test]
+        fn none_becomes_failure() {
+            let err = None::<u32>.ok_or_fail(io::Error::new(io::ErrorKind::NotFound, "gone"));
+            assert_eq!(err.unwrap_err().kind(), io::ErrorKind::NotFound);
+        }
+    }
+}
@@ -0,0 +1,448 @@
+use core::any::Any;
+use core::cell::RefCell;
+use core::fmt::{self, Display};
+use std::io::{self, Write};
+use std::panic::{self, UnwindSafe};
+use std::sync::Once;
+use std::thread::{self, JoinHandle};
+
+use {Backtrace, Compat, Error, Fail};
+use cleanup;
+
+thread_local! {
+    // A frame for each `catch_panic` call active on this thread, innermost
+    // last, holding the last panic the hook recorded during the call.
+    static CAPTURED: RefCell<Vec<Option<Capture>>> = RefCell::new(Vec::new());
+}
+
+/// The location in the source code at which a panic occurred.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PanicLocation {
+    file: String,
+    line: u32,
+    column: u32,
+}
+
+impl PanicLocation {
+    /// Returns the name of the source file.
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// Returns the line number.
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Returns the column number.
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+}
+
+impl<'a> From<&'a panic::Location<'a>> for PanicLocation {
+    fn from(location: &'a panic::Location<'a>) -> PanicLocation {
+        PanicLocation {
+            file: location.file().to_string(),
+            line: location.line(),
+            column: location.column(),
+        }
+    }
+}
+
+impl Display for PanicLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+enum Payload {
+    Message(String),
+    Error(Error),
+    Opaque,
+}
+
+/// A failure created from a panic, by `catch_panic` or `JoinHandleExt`.
+///
+/// Panics are usually raised with a message, which is returned by
+/// `message`. Panics raised with `std::panic::panic_any` and a payload other
+/// than a string have no message, unless the payload is an `Error` or a
+/// `Compat<Error>`, which becomes the cause of the `PanicFailure`.
+///
+/// The location and backtrace of the panic are recorded by a panic hook,
+/// which `catch_panic` installs the first time it is called. They are not
+/// available when the panic was not raised inside `catch_panic`, or when
+/// the panic hook has since been replaced with `std::panic::set_hook`.
+pub struct PanicFailure {
+    payload: Payload,
+    location: Option<PanicLocation>,
+    backtrace: Backtrace,
+}
+
+impl PanicFailure {
+    /// Creates a `PanicFailure` from the payload of a panic, as returned by
+    /// `std::panic::catch_unwind` or `JoinHandle::join`.
+    pub fn new(payload: Box<dyn Any + Send + 'static>) -> PanicFailure {
+        PanicFailure::with_capture(payload, None, Backtrace::none())
+    }
+
+    fn with_capture(
+        payload: Box<dyn Any + Send + 'static>,
+        location: Option<PanicLocation>,
+        backtrace: Backtrace,
+    ) -> PanicFailure {
+        let payload = match payload.downcast::<&'static str>() {
+            Ok(message) => Payload::Message(String::from(*message)),
+            Err(payload) => match payload.downcast::<String>() {
+                Ok(message) => Payload::Message(*message),
+                Err(payload) => match payload.downcast::<Error>() {
+                    Ok(error) => Payload::Error(*error),
+                    Err(payload) => match payload.downcast::<Compat<Error>>() {
+                        Ok(compat) => Payload::Error(compat.into_inner()),
+                        Err(_) => Payload::Opaque,
+                    },
+                },
+            },
+        };
+        PanicFailure { payload, location, backtrace }
+    }
+
+    /// Returns the message the panic was raised with, if the payload was a
+    /// string.
+    pub fn message(&self) -> Option<&str> {
+        match self.payload {
+            Payload::Message(ref message) => Some(message),
+            _ => None,
+        }
+    }
+
+    /// Returns the `Error` the panic was raised with, if any.
+    pub fn error(&self) -> Option<&Error> {
+        match self.payload {
+            Payload::Error(ref error) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// Returns the location of the panic, if it was recorded.
+    pub fn location(&self) -> Option<&PanicLocation> {
+        self.location.as_ref()
+    }
+}
+
+impl Fail for PanicFailure {
+    fn name(&self) -> Option<&str> {
+        Some("failure::PanicFailure")
+    }
+
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.error().map(Error::as_fail)
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        Some(&self.backtrace)
+    }
+}
+
+impl fmt::Debug for PanicFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PanicFailure")
+            .field("message", &self.message())
+            .field("error", &self.error())
+            .field("location", &self.location.as_ref().map(|location| location.to_string()))
+            .finish()
+    }
+}
+
+impl Display for PanicFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_panic(f, self.message(), self.error(), self.location())
+    }
+}
+
+fn fmt_panic(
+    f: &mut fmt::Formatter,
+    message: Option<&str>,
+    error: Option<&Error>,
+    location: Option<&PanicLocation>,
+) -> fmt::Result {
+    match (message, error) {
+        (Some(message), _) => write!(f, "panicked at '{}'", message)?,
+        (None, Some(error)) => write!(f, "panicked at '{}'", error)?,
+        (None, None) => write!(f, "panicked with a payload of an opaque type")?,
+    }
+    match location {
+        Some(location) => write!(f, ", {}", location),
+        None => Ok(()),
+    }
+}
+
+/// What the hook installed by `install_panic_hook` prints for a panic.
+struct PanicReport<'a> {
+    thread: Option<&'a str>,
+    message: Option<&'a str>,
+    error: Option<&'a Error>,
+    location: Option<&'a PanicLocation>,
+    backtrace: &'a Backtrace,
+}
+
+impl<'a> Display for PanicReport<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "thread '{}' ", self.thread.unwrap_or("<unnamed>"))?;
+        fmt_panic(f, self.message, self.error, self.location)?;
+        if let Some(error) = self.error {
+            for cause in error.iter_causes() {
+                write!(f, "\ncaused by: {}", cause)?;
+            }
+            for suppressed in error.suppressed() {
+                write!(f, "\nsuppressed: {}", suppressed)?;
+                for cause in suppressed.iter_causes() {
+                    write!(f, "\n    caused by: {}", cause)?;
+                }
+            }
+        }
+        if !self.backtrace.is_empty() {
+            write!(f, "\n\n{:?}", self.backtrace)?;
+        }
+        Ok(())
+    }
+}
+
+/// What the hook records about a panic raised inside `catch_panic`.
+struct Capture {
+    thread: Option<String>,
+    message: Option<String>,
+    location: Option<PanicLocation>,
+    backtrace: Backtrace,
+}
+
+impl Capture {
+    /// Prints a panic which was caught before it reached `catch_panic`, as
+    /// `install_panic_hook` would have.
+    fn print(&self) {
+        let report = PanicReport {
+            thread: self.thread.as_deref(),
+            message: self.message.as_deref(),
+            error: None,
+            location: self.location.as_ref(),
+            backtrace: &self.backtrace,
+        };
+        let _ = writeln!(io::stderr(), "{}", report);
+    }
+}
+
+/// Returns the message of a panic raised with a string.
+fn payload_message(payload: &(dyn Any + Send)) -> Option<&str> {
+    payload.downcast_ref::<&'static str>().cloned()
+        .or_else(|| payload.downcast_ref::<String>().map(|message| &message[..]))
+}
+
+/// Returns the error of a panic raised with an `Error` or a `Compat<Error>`.
+fn payload_error(payload: &(dyn Any + Send)) -> Option<&Error> {
+    payload.downcast_ref::<Error>()
+        .or_else(|| payload.downcast_ref::<Compat<Error>>().map(Compat::get_ref))
+}
+
+/// Records a panic in the frame of the innermost `catch_panic`, returning
+/// false if this thread is not in `catch_panic`.
+///
+/// A panic already in the frame was caught before it reached `catch_panic`,
+/// such as by `std::panic::catch_unwind`, so it is printed instead of being
+/// lost. This takes the payload and location rather than the hook's
+/// argument, whose type was renamed from `PanicInfo` to `PanicHookInfo` in
+/// Rust 1.81.
+fn capture(payload: &(dyn Any + Send), location: Option<&panic::Location>) -> bool {
+    let capture = || Capture {
+        thread: thread::current().name().map(String::from),
+        message: payload_message(payload).map(String::from)
+            .or_else(|| payload_error(payload).map(Error::to_string)),
+        location: location.map(PanicLocation::from),
+        backtrace: Backtrace::new(),
+    };
+    CAPTURED.try_with(|captured| match captured.borrow_mut().last_mut() {
+        Some(frame) => {
+            if let Some(caught) = frame.replace(capture()) {
+                caught.print();
+            }
+            true
+        }
+        None => false,
+    }).unwrap_or(false)
+}
+
+fn install_capture_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !capture(info.payload(), info.location()) {
+                previous(info);
+            }
+        }));
+    });
+}
+
+/// Installs a panic hook which prints panics in the same way as `Error`.
+///
+/// This replaces the current panic hook. Panics are printed to standard
+/// error with the message and location of the panic, followed by a
+/// backtrace, which like the backtraces of `Error` is only captured when
+/// `RUST_FAILURE_BACKTRACE` or `RUST_BACKTRACE` enables it.
+///
+/// When the panic was raised with an `Error` or a `Compat<Error>` as its
+/// payload, for example with `std::panic::panic_any`, the whole chain of
+/// the error is printed, followed by the errors it suppressed and the
+/// backtrace of the error if it has one.
+///
+/// Panics raised inside `catch_panic` are not printed, unless they are caught
+/// before they reach it, as by `std::panic::catch_unwind`.
+pub fn install_panic_hook() {
+    panic::set_hook(Box::new(|info| {
+        let payload = info.payload();
+        if capture(payload, info.location()) {
+            return;
+        }
+        let message = payload_message(payload);
+        let error = payload_error(payload);
+        let location = info.location().map(PanicLocation::from);
+        let captured;
+        let backtrace = match error {
+            Some(error) if !error.backtrace().is_empty() => error.backtrace(),
+            _ => {
+                captured = Backtrace::new();
+                &captured
+            }
+        };
+        let thread = thread::current();
+        let report = PanicReport {
+            thread: thread.name(),
+            message,
+            error,
+            location: location.as_ref(),
+            backtrace,
+        };
+        let _ = writeln!(io::stderr(), "{}", report);
+    }));
+}
+
+/// Calls a closure, turning a panic into an `Error` containing a
+/// `PanicFailure`.
+///
+/// The panic is not printed by the panic hook, since it is returned as an
+/// error instead. Panics inside the closure which are caught before they
+/// reach `catch_panic`, as by `std::panic::catch_unwind`, are still printed,
+/// although only once the panic hook sees the next panic or `catch_panic`
+/// returns, and in the format of `install_panic_hook`. The failures of the cleanups of the `CleanupGuard`s
+/// dropped while unwinding are attached to the error as suppressed. As with `std::panic::catch_unwind`, this only catches
+/// panics which unwind, and not those which abort the process.
+///
+/// # Examples
+///
+/// ```
+/// use failure::{catch_panic, PanicFailure};
+///
+/// let err = catch_panic(|| -> u32 { panic!("worker {} failed", 3) }).unwrap_err();
+/// let panic = err.downcast_ref::<PanicFailure>().unwrap();
+/// assert_eq!(panic.message(), Some("worker 3 failed"));
+/// assert!(panic.location().is_some());
+/// ```
+pub fn catch_panic<F, R>(f: F) -> Result<R, Error>
+where
+    F: FnOnce() -> R + UnwindSafe,
+{
+    install_capture_hook();
+    CAPTURED.with(|captured| captured.borrow_mut().push(None));
+    let (result, dropped) = cleanup::collect_dropped(|| panic::catch_unwind(f));
+    let capture = CAPTURED.with(|captured| captured.borrow_mut().pop()).and_then(|capture| capture);
+    match result {
+        Ok(value) => {
+            if let Some(caught) = capture {
+                caught.print();
+            }
+            dropped.into_iter().for_each(cleanup::record_dropped);
+            Ok(value)
+        }
+        Err(payload) => {
+            let (location, backtrace) = capture
+                .map(|capture| (capture.location, capture.backtrace))
+                .unwrap_or_else(|| (None, Backtrace::none()));
+            let error = Error::from(PanicFailure::with_capture(payload, location, backtrace));
+            Err(dropped.into_iter().fold(error, Error::with_suppressed))
+        }
+    }
+}
+
+/// Extension methods for `JoinHandle`, to turn the panic of a thread into an
+/// `Error`.
+pub trait JoinHandleExt<T> {
+    /// Waits for the thread to finish, turning a panic into an `Error`
+    /// containing a `PanicFailure`.
+    ///
+    /// The panic is only known from its payload, so the `PanicFailure` has no
+    /// location or backtrace. To record them, run the body of the thread in
+    /// `catch_panic` instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::thread;
+    /// use failure::JoinHandleExt;
+    ///
+    /// let handle = thread::spawn(|| -> u32 { panic!("worker failed") });
+    /// let err = handle.join_failure().unwrap_err();
+    /// assert_eq!(err.to_string(), "panicked at 'worker failed'");
+    /// ```
+    fn join_failure(self) -> Result<T, Error>;
+}
+
+impl<T> JoinHandleExt<T> for JoinHandle<T> {
+    fn join_failure(self) -> Result<T, Error> {
+        self.join().map_err(|payload| Error::from(PanicFailure::new(payload)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PanicLocation, PanicReport};
+    use {err_msg, Backtrace};
+
+    #[test]
+    fn report_prints_chain() {
+        let error = err_msg("disk full").wrap("writing index");
+        let location = PanicLocation { file: "src/main.rs".to_string(), line: 3, column: 5 };
+        let report = PanicReport {
+            thread: Some("main"),
+            message: None,
+            error: Some(&error),
+            location: Some(&location),
+            backtrace: &Backtrace::none(),
+        };
+        assert_eq!(
+            report.to_string(),
+            "thread 'main' panicked at 'writing index', src/main.rs:3:5\ncaused by: disk full",
+        );
+
+        let report = PanicReport { thread: None, message: Some("oops"), error: None, ..report };
+        assert_eq!(report.to_string(), "thread '<unnamed>' panicked at 'oops', src/main.rs:3:5");
+    }
+
+    #[test]
+    fn report_prints_suppressed() {
+        let error = err_msg("disk full")
+            .with_suppressed(err_msg("lock poisoned").wrap("unlocking"))
+            .with_suppressed(err_msg("rollback failed"));
+        let report = PanicReport {
+            thread: Some("main"),
+            message: None,
+            error: Some(&error),
+            location: None,
+            backtrace: &Backtrace::none(),
+        };
+        assert_eq!(
+            report.to_string(),
+            "thread 'main' panicked at 'disk full'\n\
+             suppressed: unlocking\n    caused by: lock poisoned\n\
+             suppressed: rollback failed",
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use core::fmt::{self, Display, Write};
+
+use {AsFail, Fail, Field, ValueRef};
+#[cfg(feature = "alloc")]
+use Error;
+
+/// Renders the chain of a failure as JSON, with its structured fields.
+///
+/// Each failure in the chain, starting with the outermost, is rendered as an
+/// object with its message, its name and error code when it has them, and its
+/// fields as an object of typed values. The errors suppressed by an `Error`
+/// follow in a `suppressed` array, each rendered as a report of its own. This
+/// is meant for loggers and error reporters which store errors as structured
+/// data:
+///
+/// ```
+/// use failure::{Context, Error, JsonReport};
+///
+/// let err: Error = Context::new("reading config").field("attempt", 3).into();
+/// assert_eq!(
+///     JsonReport::new(&err).to_string(),
+///     r#"{"chain":[{"message":"reading config","name":"failure::Context","fields":{"attempt":3}}]}"#
+/// );
+/// ```
+pub struct JsonReport<'a> {
+    fail: &'a dyn Fail,
+    #[cfg(feature = "alloc")]
+    suppressed: &'a [Error],
+}
+
+impl<'a> JsonReport<'a> {
+    /// Creates a report of a failure, or of the failure in an `Error` and the
+    /// errors it suppressed.
+    pub fn new<F: AsFail + ?Sized>(fail: &'a F) -> JsonReport<'a> {
+        JsonReport {
+            fail: fail.as_fail(),
+            #[cfg(feature = "alloc")]
+            suppressed: fail.__private_suppressed__(),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn fmt_suppressed(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.suppressed.is_empty() {
+            return Ok(());
+        }
+        f.write_str(",\"suppressed\":[")?;
+        for (i, error) in self.suppressed.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", JsonReport::new(error))?;
+        }
+        f.write_str("]")
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    fn fmt_suppressed(&self, _: &mut fmt::Formatter) -> fmt::Result {
+        Ok(())
+    }
+}
+
+impl<'a> Display for JsonReport<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("{\"chain\":[")?;
+        for (i, fail) in self.fail.iter_chain().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            f.write_str("{\"message\":")?;
+            write_str(f, fail)?;
+            if let Some(name) = fail.name() {
+                f.write_str(",\"name\":")?;
+                write_str(f, name)?;
+            }
+            if let Some(code) = fail.code() {
+                f.write_str(",\"code\":")?;
+                write_str(f, code)?;
+            }
+            f.write_str(",\"fields\":{")?;
+            for (i, field) in fail.fields().iter().enumerate() {
+                if i > 0 {
+                    f.write_str(",")?;
+                }
+                write_field(f, field)?;
+            }
+            f.write_str("}}")?;
+        }
+        f.write_str("]")?;
+        self.fmt_suppressed(f)?;
+        f.write_str("}")
+    }
+}
+
+fn write_field(f: &mut fmt::Formatter, field: &Field) -> fmt::Result {
+    write_str(f, field.key())?;
+    f.write_str(":")?;
+    match field.value().get() {
+        ValueRef::Bool(value) => write!(f, "{}", value),
+        ValueRef::I64(value) => write!(f, "{}", value),
+        ValueRef::U64(value) => write!(f, "{}", value),
+        ValueRef::F64(value) if value.is_finite() => write!(f, "{}", value),
+        ValueRef::F64(_) => f.write_str("null"),
+        ValueRef::Str(value) => write_str(f, value),
+    }
+}
+
+// Writes a value as a JSON string, escaping it as it is formatted.
+fn write_str<D: Display + ?Sized>(f: &mut fmt::Formatter, value: &D) -> fmt::Result {
+    f.write_str("\"")?;
+    write!(Escape(f), "{}", value)?;
+    f.write_str("\"")
+}
+
+struct Escape<'a, 'b: 'a>(&'a mut fmt::Formatter<'b>);
+
+impl<'a, 'b> Write for Escape<'a, 'b> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            match c {
+                '"' => self.0.write_str("\\\"")?,
+                '\\' => self.0.write_str("\\\\")?,
+                '\n' => self.0.write_str("\\n")?,
+                '\r' => self.0.write_str("\\r")?,
+                '\t' => self.0.write_str("\\t")?,
+                c if (c as u32) < 0x20 => write!(self.0, "\\u{:04x}", c as u32)?,
+                c => self.0.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,8 @@
-use core::fmt::Display;
+use core::fmt::{self, Display};
 
-use {Compat, Context, Fail};
+use {Compat, Context, Fail, LazyContext};
+#[cfg(feature = "alloc")]
+use {Error, Value};
 
 /// Extension methods for `Result`.
 pub trait ResultExt<T, E> {
@@ -147,6 +149,49 @@ pub trait ResultExt<T, E> {
     where
         F: FnOnce(&E) -> D,
         D: Display + Send + Sync + 'static;
+
+    /// Wraps the error type in a context type which is only formatted when
+    /// it is displayed.
+    ///
+    /// Unlike `with_context`, nothing is formatted when the error occurs, so
+    /// errors which are handled without being displayed cost no formatting.
+    /// See `LazyContext`.
+    fn lazy_context<F>(self, f: F) -> Result<T, Context<LazyContext<F>>>
+    where
+        Self: Sized,
+        F: Fn(&mut fmt::Formatter) -> fmt::Result + Send + Sync + 'static,
+    {
+        self.context(LazyContext::new(f))
+    }
+
+    /// Wraps the error type in a context type with structured key-value
+    /// fields.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use failure::{Error, ResultExt, Value};
+    ///
+    /// let path = "/nonexistent/app.toml";
+    /// let err: Error = File::open(path)
+    ///     .context_with("reading config", &[("path", Value::from(path)), ("attempt", Value::from(3))])
+    ///     .unwrap_err()
+    ///     .into();
+    /// assert_eq!(err.fields().next().unwrap().to_string(), "path=\"/nonexistent/app.toml\"");
+    /// ```
+    #[cfg(feature = "alloc")]
+    fn context_with<D>(self, context: D, fields: &[(&'static str, Value)]) -> Result<T, Context<D>>
+    where
+        Self: Sized,
+        D: Display + Send + Sync + 'static,
+    {
+        self.context(context).map_err(|context| {
+            fields.iter().fold(context, |context, &(key, ref value)| {
+                context.field(key, value.clone())
+            })
+        })
+    }
 }
 
 impl<T, E> ResultExt<T, E> for Result<T, E>
@@ -176,9 +221,7 @@ where
     }
 }
 
-with_std! {
-    use Error;
-
+with_alloc! {
     impl<T> ResultExt<T, Error> for Result<T, Error> {
         fn compat(self) -> Result<T, Compat<Error>> {
             self.map_err(|err| err.compat())
@@ -0,0 +1,229 @@
+use core::fmt::{self, Display};
+use std::thread;
+use std::time::Duration;
+
+use {Error, Fail};
+
+/// How often, and how long apart, `retry` attempts an operation.
+///
+/// By default, an operation is attempted at most 3 times, and waits at most
+/// an hour before each retry.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use failure::Backoff;
+///
+/// let backoff = Backoff::exponential(Duration::from_millis(100))
+///     .max_delay(Duration::from_secs(1))
+///     .max_attempts(6);
+/// assert_eq!(backoff.delay(1), Duration::from_millis(100));
+/// assert_eq!(backoff.delay(3), Duration::from_millis(400));
+/// assert_eq!(backoff.delay(5), Duration::from_secs(1));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Backoff {
+    max_attempts: u32,
+    initial: Duration,
+    exponential: bool,
+    max_delay: Duration,
+}
+
+// The default upper bound on the delay before a retry, in seconds, so that
+// an exponential backoff which overflows does not wait forever.
+const MAX_DELAY_SECS: u64 = 60 * 60;
+
+impl Backoff {
+    /// Retries immediately, without waiting.
+    pub fn none() -> Backoff {
+        Backoff::fixed(Duration::from_secs(0))
+    }
+
+    /// Waits the same delay before each retry.
+    pub fn fixed(delay: Duration) -> Backoff {
+        Backoff {
+            max_attempts: 3,
+            initial: delay,
+            exponential: false,
+            max_delay: Duration::from_secs(MAX_DELAY_SECS),
+        }
+    }
+
+    /// Waits `initial` before the first retry, and doubles the delay before
+    /// each following retry.
+    pub fn exponential(initial: Duration) -> Backoff {
+        Backoff {
+            max_attempts: 3,
+            initial,
+            exponential: true,
+            max_delay: Duration::from_secs(MAX_DELAY_SECS),
+        }
+    }
+
+    /// Sets the number of times the operation is attempted, including the
+    /// first attempt.
+    pub fn max_attempts(self, max_attempts: u32) -> Backoff {
+        Backoff { max_attempts, ..self }
+    }
+
+    /// Sets an upper bound on the delay before each retry, instead of an
+    /// hour.
+    pub fn max_delay(self, max_delay: Duration) -> Backoff {
+        Backoff { max_delay, ..self }
+    }
+
+    /// Returns the delay before the given retry, counting from 1.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = if self.exponential {
+            1u32.checked_shl(retry.saturating_sub(1))
+        } else {
+            Some(1)
+        };
+        match factor.and_then(|factor| self.initial.checked_mul(factor)) {
+            Some(delay) => delay.min(self.max_delay),
+            None => self.max_delay,
+        }
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Backoff {
+        Backoff::none()
+    }
+}
+
+/// The failed attempts made by `retry` before the attempt which produced the
+/// final error.
+///
+/// `retry` attaches the history to the final error as a suppressed error, so
+/// that the final error stays the failure of the returned `Error`. It can be
+/// found with `RetryHistory::find`, and displays as `failed after N attempts`.
+#[derive(Debug)]
+pub struct RetryHistory {
+    errors: Vec<Error>,
+}
+
+impl RetryHistory {
+    /// Returns the retry history attached to an error, if it has one.
+    pub fn find(error: &Error) -> Option<&RetryHistory> {
+        error.suppressed()
+            .iter()
+            .filter_map(|suppressed| suppressed.downcast_ref::<RetryHistory>())
+            .next()
+    }
+
+    /// Returns the number of attempts made, including the final attempt.
+    pub fn attempts(&self) -> usize {
+        self.errors.len() + 1
+    }
+
+    /// Returns the errors of the attempts before the final attempt, in the
+    /// order they occurred.
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+}
+
+impl Fail for RetryHistory {
+    fn name(&self) -> Option<&str> {
+        Some("failure::RetryHistory")
+    }
+}
+
+impl Display for RetryHistory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "failed after {} attempts", self.attempts())
+    }
+}
+
+/// Calls an operation until it succeeds, retrying it while it fails with a
+/// transient error.
+///
+/// The operation is retried as long as its error `is_transient` and fewer
+/// than the maximum number of attempts of `backoff` have been made, waiting
+/// the delay given by `backoff` before each retry. If the operation was
+/// retried and still failed, its last error is returned, with a
+/// `RetryHistory` recording the errors of the earlier attempts attached as a
+/// suppressed error.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use] extern crate failure_derive;
+/// # extern crate failure;
+/// use failure::{retry, Backoff, RetryHistory};
+///
+/// #[derive(Debug, Fail)]
+/// #[fail(display = "connection reset")]
+/// #[fail(transient)]
+/// struct ConnectionReset;
+///
+/// # fn main() {
+/// let mut calls = 0;
+/// let err = retry(&Backoff::none(), || -> Result<(), _> {
+///     calls += 1;
+///     Err(ConnectionReset)
+/// }).unwrap_err();
+/// assert_eq!(calls, 3);
+/// assert_eq!(err.to_string(), "connection reset");
+/// assert_eq!(RetryHistory::find(&err).unwrap().errors().len(), 2);
+/// # }
+/// ```
+pub fn retry<T, E, F>(backoff: &Backoff, mut operation: F) -> Result<T, Error>
+where
+    F: FnMut() -> Result<T, E>,
+    E: Into<Error>,
+{
+    let mut errors = Vec::new();
+    loop {
+        let error = match operation() {
+            Ok(value) => return Ok(value),
+            Err(error) => error.into(),
+        };
+        let attempts = errors.len() as u32 + 1;
+        if !error.is_transient() || attempts >= backoff.max_attempts {
+            if errors.is_empty() {
+                return Err(error);
+            }
+            return Err(error.with_suppressed(RetryHistory { errors }));
+        }
+        let delay = backoff.delay(attempts);
+        if delay > Duration::from_secs(0) {
+            thread::sleep(delay);
+        }
+        errors.push(error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use super::Backoff;
+
+    #[test]
+    fn delays() {
+        let fixed = Backoff::fixed(Duration::from_millis(10));
+        assert_eq!(fixed.delay(1), Duration::from_millis(10));
+        assert_eq!(fixed.delay(9), Duration::from_millis(10));
+
+        let exponential = Backoff::exponential(Duration::from_secs(1));
+        assert_eq!(exponential.delay(2), Duration::from_secs(2));
+        assert_eq!(exponential.delay(12), Duration::from_secs(2048));
+        let capped = exponential.max_delay(Duration::from_secs(60));
+        assert_eq!(capped.delay(200), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn overflowing_delays_are_capped() {
+        let hour = Duration::from_secs(60 * 60);
+        let exponential = Backoff::exponential(Duration::from_secs(1));
+        assert_eq!(exponential.delay(13), hour);
+        assert_eq!(exponential.delay(33), hour);
+        assert_eq!(exponential.delay(200), hour);
+        assert_eq!(exponential.delay(u32::MAX), hour);
+        let huge = Backoff::exponential(Duration::from_secs(u64::MAX / 2));
+        assert_eq!(huge.delay(3), hour);
+        assert_eq!(Backoff::fixed(Duration::from_secs(u64::MAX)).delay(1), hour);
+    }
+}
@@ -0,0 +1,145 @@
+use std::any::{type_name, Any};
+use std::error::Error as StdError;
+use std::fmt::{self, Debug, Display};
+use std::io;
+use std::sync::Arc;
+
+use Fail;
+
+/// A failure recording the message and the chain of causes of an error
+/// which cannot be a `Fail` itself, because it is not `Send`, `Sync` or
+/// `'static`.
+///
+/// The `Display` and `Debug` output of the error and of each error in its
+/// `source` chain are formatted once, when the snapshot is taken, and the
+/// error is then dropped. Each error in the chain becomes a separate
+/// `Snapshot` in the chain of this failure.
+///
+/// Unlike `SyncFailure`, a snapshot takes no lock to be displayed, and is
+/// cheap to clone. It is the better choice for errors which are only
+/// displayed, rather than inspected.
+#[derive(Clone)]
+pub struct Snapshot {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    name: Option<&'static str>,
+    kind: Option<io::ErrorKind>,
+    display: String,
+    debug: String,
+    cause: Option<Snapshot>,
+}
+
+impl Snapshot {
+    /// Takes a snapshot of an error and its chain of sources.
+    pub fn new<E: StdError + ?Sized>(error: &E) -> Snapshot {
+        Snapshot::with_name(error, Some(type_name::<E>()), None)
+    }
+
+    fn with_name(
+        error: &(impl StdError + ?Sized),
+        name: Option<&'static str>,
+        kind: Option<io::ErrorKind>,
+    ) -> Snapshot {
+        let inner = Inner {
+            name,
+            kind,
+            display: error.to_string(),
+            debug: format!("{:?}", error),
+            // The concrete types of the sources are not known, but they can
+            // be downcast to `io::Error` to keep its kind.
+            cause: error.source().map(|source| {
+                let kind = source.downcast_ref::<io::Error>().map(io::Error::kind);
+                Snapshot::with_name(source, None, kind)
+            }),
+        };
+        Snapshot { inner: Arc::new(inner) }
+    }
+
+    /// Returns the message of the error, as formatted by `Display`.
+    pub fn message(&self) -> &str {
+        &self.inner.display
+    }
+
+    /// Returns the error as formatted by `Debug`.
+    pub fn debug(&self) -> &str {
+        &self.inner.debug
+    }
+
+    /// Returns the kind of the error, if it was an `io::Error`.
+    ///
+    /// This is only known for the sources of the error passed to `new`,
+    /// since that error itself need not be `'static` to be downcast.
+    pub fn io_error_kind(&self) -> Option<io::ErrorKind> {
+        self.inner.kind
+    }
+}
+
+impl Fail for Snapshot {
+    fn name(&self) -> Option<&str> {
+        self.inner.name
+    }
+
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.inner.cause.as_ref().map(|cause| cause as &dyn Fail)
+    }
+
+    fn __private_get_kind__(&self) -> Option<&dyn Any> {
+        self.inner.kind.as_ref().map(|kind| kind as &dyn Any)
+    }
+}
+
+impl Debug for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.inner.debug)
+    }
+}
+
+impl Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&self.inner.display, f)
+    }
+}
+
+/// Extension methods for `Result`s whose error cannot be a `Fail`.
+pub trait SnapshotExt<T> {
+    /// Replaces the error with a `Snapshot` of it, so that it can be used
+    /// with `?` in functions returning `Error`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::error::Error as StdError;
+    /// use std::fmt;
+    /// use std::rc::Rc;
+    /// use failure::{Error, SnapshotExt};
+    ///
+    /// #[derive(Debug)]
+    /// struct ScriptError {
+    ///     source: Rc<str>,
+    /// }
+    ///
+    /// impl fmt::Display for ScriptError {
+    ///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    ///         write!(f, "script error in `{}`", self.source)
+    ///     }
+    /// }
+    ///
+    /// impl StdError for ScriptError {}
+    ///
+    /// fn run() -> Result<(), Error> {
+    ///     Err(ScriptError { source: Rc::from("x +") }).snapshot_err()?;
+    ///     Ok(())
+    /// }
+    ///
+    /// assert_eq!(run().unwrap_err().to_string(), "script error in `x +`");
+    /// ```
+    fn snapshot_err(self) -> Result<T, Snapshot>;
+}
+
+impl<T, E: StdError> SnapshotExt<T> for Result<T, E> {
+    fn snapshot_err(self) -> Result<T, Snapshot> {
+        self.map_err(|error| Snapshot::new(&error))
+    }
+}
@@ -13,6 +13,10 @@ use std::sync::Mutex;
 /// be locked for every `Debug`/`Display`. Therefore, this should be
 /// something of a last resort in making the error work with `failure`.
 ///
+/// Errors which are not `Send` either can be converted into a `Snapshot`
+/// instead, and the errors of locks and channels into a `LockFailure` or a
+/// `SendFailure`.
+///
 pub struct SyncFailure<T> {
     inner: Mutex<T>,
 }
@@ -94,4 +98,8 @@ where
     }
 }
 
-impl<E: Error + Send + 'static> Fail for SyncFailure<E> {}
+impl<E: Error + Send + 'static> Fail for SyncFailure<E> {
+    fn name(&self) -> Option<&str> {
+        Some(::std::any::type_name::<E>())
+    }
+}
@@ -0,0 +1,89 @@
+#![cfg(feature = "std")]
+
+extern crate failure;
+
+use std::cell::RefCell;
+use std::error::Error as StdError;
+use std::fmt;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+
+use failure::{Error, LockExt, LockFailure, RwLockExt, SendFailure, Snapshot, SnapshotExt};
+
+#[derive(Debug)]
+struct Inner(Rc<RefCell<u32>>);
+
+impl fmt::Display for Inner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "value {} out of range", self.0.borrow())
+    }
+}
+
+impl StdError for Inner {}
+
+#[derive(Debug)]
+struct Outer(Inner);
+
+impl fmt::Display for Outer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "evaluation failed")
+    }
+}
+
+impl StdError for Outer {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.0)
+    }
+}
+
+fn evaluate() -> Result<(), Outer> {
+    Err(Outer(Inner(Rc::new(RefCell::new(7)))))
+}
+
+#[test]
+fn snapshot_keeps_chain() {
+    let err: Error = evaluate().snapshot_err().unwrap_err().into();
+    let chain: Vec<_> = err.iter_chain().map(|fail| fail.to_string()).collect();
+    assert_eq!(chain, ["evaluation failed", "value 7 out of range"]);
+    assert_eq!(err.downcast_ref::<Snapshot>().unwrap().message(), "evaluation failed");
+}
+
+#[test]
+fn poisoned_lock() {
+    let mutex = Arc::new(Mutex::new(0));
+    let lock = Arc::new(RwLock::new(0));
+    let (mutex2, lock2) = (mutex.clone(), lock.clone());
+    let _ = thread::spawn(move || {
+        let _guard = mutex2.lock().unwrap();
+        let _write = lock2.write().unwrap();
+        panic!("poisoning the locks");
+    }).join();
+
+    let err: Error = mutex.lock_fallible().unwrap_err().into();
+    assert_eq!(err.to_string(), "poisoned lock: another task failed inside");
+    assert_eq!(err.downcast_ref::<LockFailure>(), Some(&LockFailure::Poisoned));
+    assert!(!err.is_transient());
+    assert_eq!(lock.read_fallible().unwrap_err(), LockFailure::Poisoned);
+    assert_eq!(lock.write_fallible().unwrap_err(), LockFailure::Poisoned);
+}
+
+#[test]
+fn try_lock() {
+    let mutex = Mutex::new(0);
+    let _guard = mutex.lock_fallible().unwrap();
+    let err: Error = mutex.try_lock_fallible().unwrap_err().into();
+    assert_eq!(err.downcast_ref::<LockFailure>(), Some(&LockFailure::WouldBlock));
+    assert!(err.is_transient());
+}
+
+#[test]
+fn send_failure() {
+    let (sender, receiver) = mpsc::sync_channel::<Rc<u32>>(0);
+    let full = sender.try_send(Rc::new(1)).map_err(SendFailure::from).unwrap_err();
+    assert_eq!(full, SendFailure::Full);
+    drop(receiver);
+    let err: Error = sender.send(Rc::new(2)).map_err(SendFailure::from).unwrap_err().into();
+    assert_eq!(err.to_string(), "sending on a closed channel");
+}
@@ -0,0 +1,91 @@
+#![cfg(all(feature = "std", feature = "derive"))]
+
+#[macro_use]
+extern crate failure;
+
+use std::borrow::Cow;
+
+use failure::{Error, ErrorMessage, Fail};
+
+#[derive(Fail, Debug, PartialEq)]
+enum MyError {
+    #[fail(display = "not found")]
+    NotFound,
+    #[fail(display = "too large: {}", _0)]
+    TooLarge(u32),
+}
+
+fn bail_failure() -> Result<(), Error> {
+    bail!(MyError::NotFound)
+}
+
+fn bail_concrete() -> Result<(), MyError> {
+    bail!(MyError::NotFound)
+}
+
+fn bail_error() -> Result<(), Error> {
+    let err: Error = MyError::NotFound.into();
+    bail!(err)
+}
+
+fn bail_message() -> Result<(), Error> {
+    bail!("not found")
+}
+
+fn ensure_failure(size: u32) -> Result<u32, MyError> {
+    ensure!(size < 10, MyError::TooLarge(size));
+    Ok(size)
+}
+
+fn ensure_message(size: u32) -> Result<u32, Error> {
+    ensure!(size < 10, "too large");
+    Ok(size)
+}
+
+#[test]
+fn bail_keeps_failures() {
+    let err = bail_failure().unwrap_err();
+    assert_eq!(err.downcast_ref::<MyError>(), Some(&MyError::NotFound));
+    assert_eq!(err.name(), Some("bail::MyError"));
+
+    assert_eq!(bail_concrete(), Err(MyError::NotFound));
+
+    let err = bail_error().unwrap_err();
+    assert_eq!(err.downcast_ref::<MyError>(), Some(&MyError::NotFound));
+}
+
+#[test]
+fn bail_wraps_messages() {
+    let err = bail_message().unwrap_err();
+    assert_eq!(err.to_string(), "not found");
+    assert_eq!(err.name(), Some("failure::ErrorMessage"));
+    assert!(err.is_message());
+    assert!(!bail_failure().unwrap_err().is_message());
+
+    let err = format_err!("code {}", 7);
+    assert_eq!(err.downcast_ref::<ErrorMessage>().unwrap().message(), "code 7");
+}
+
+#[test]
+fn constant_messages_are_borrowed() {
+    fn is_borrowed(err: Error) -> bool {
+        match err.downcast::<ErrorMessage>().unwrap().into_message() {
+            Cow::Borrowed(_) => true,
+            Cow::Owned(_) => false,
+        }
+    }
+
+    assert!(is_borrowed(bail_message().unwrap_err()));
+    assert!(is_borrowed(format_err!("not found")));
+    let code = 7;
+    assert!(!is_borrowed(format_err!("code {}", code)));
+    assert_eq!(format_err!("code {{code}}").to_string(), "code {code}");
+    assert_eq!(format_err!("code {code}", code = code).to_string(), "code 7");
+}
+
+#[test]
+fn ensure_keeps_failures() {
+    assert_eq!(ensure_failure(3), Ok(3));
+    assert_eq!(ensure_failure(12), Err(MyError::TooLarge(12)));
+    assert_eq!(ensure_message(12).unwrap_err().to_string(), "too large");
+}
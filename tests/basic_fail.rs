@@ -1,4 +1,5 @@
-#[macro_use]
+#![cfg(all(feature = "std", feature = "derive"))]
+
 extern crate failure;
 
 use failure::Fail;
@@ -17,5 +18,27 @@ fn test_name() {
     let ctx = err.context("whatever");
 
     assert_eq!(ctx.to_string(), "whatever");
-    assert_eq!(ctx.name(), Some("basic_fail::MyError"));
-}
\ No newline at end of file
+    assert_eq!(ctx.name(), Some("failure::Context"));
+    assert_eq!(ctx.cause().unwrap().name(), Some("basic_fail::MyError"));
+}
+
+#[test]
+fn test_fallback_names() {
+    use std::io;
+    use failure::{Context, Error, SyncFailure};
+
+    let err = io::Error::new(io::ErrorKind::NotFound, "test");
+    assert_eq!(err.name(), Some(std::any::type_name::<io::Error>()));
+
+    let err = Error::from(io::Error::new(io::ErrorKind::NotFound, "test"));
+    assert_eq!(err.name(), Some(std::any::type_name::<io::Error>()));
+
+    let err = SyncFailure::new(io::Error::new(io::ErrorKind::NotFound, "test"));
+    assert_eq!(err.name(), Some(std::any::type_name::<io::Error>()));
+
+    let err = Error::from_boxed_compat(Box::new(io::Error::new(io::ErrorKind::NotFound, "test")));
+    assert!(err.name().is_some());
+
+    let ctx = Context::new("whatever");
+    assert_eq!(ctx.name(), Some("failure::Context"));
+}
@@ -0,0 +1,65 @@
+#![cfg(all(feature = "std", feature = "derive"))]
+
+#[macro_use]
+extern crate failure;
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+
+use failure::{Compat, Error, ResultExt};
+
+#[derive(Debug, Fail)]
+#[fail(display = "invalid token")]
+struct InvalidToken;
+
+#[derive(Debug)]
+struct LegacyError;
+
+impl fmt::Display for LegacyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "legacy error")
+    }
+}
+
+impl StdError for LegacyError {}
+
+fn boxed(error: Error) -> Box<dyn StdError + Send + Sync> {
+    error.into()
+}
+
+#[test]
+fn boxed_error_is_unwrapped() {
+    let err = Error::from(Err::<(), _>(InvalidToken).context("parsing").unwrap_err());
+    let mut err = Error::from_boxed_compat(boxed(err));
+    for _ in 0..3 {
+        err = Error::from_boxed_compat(boxed(err));
+    }
+    assert_eq!(err.to_string(), "parsing");
+    assert_eq!(err.iter_chain().count(), 2);
+    assert!(err.find_root_cause().downcast_ref::<InvalidToken>().is_some());
+}
+
+#[test]
+fn compat_is_unwrapped() {
+    let compat: Compat<Error> = Error::from(InvalidToken).compat();
+    let err = Error::from(compat);
+    assert!(err.downcast_ref::<InvalidToken>().is_some());
+}
+
+#[test]
+fn foreign_box_is_returned() {
+    let err = Error::from_boxed_compat(Box::new(LegacyError));
+    assert_eq!(err.to_string(), "legacy error");
+    let boxed = boxed(err);
+    assert!(boxed.downcast_ref::<LegacyError>().is_some());
+
+    let boxed: Box<dyn StdError> = Error::from_boxed_compat(Box::new(LegacyError)).into();
+    assert!(boxed.downcast_ref::<LegacyError>().is_some());
+}
+
+#[test]
+fn boxed_io_error_can_be_downcast() {
+    let err = Error::from_boxed_compat(Box::new(io::Error::new(io::ErrorKind::NotFound, "test")));
+    assert_eq!(err.downcast_ref::<io::Error>().unwrap().kind(), io::ErrorKind::NotFound);
+}
@@ -0,0 +1,72 @@
+#![cfg(feature = "std")]
+
+#[macro_use]
+extern crate failure;
+
+use failure::{DataPathExt, Error, PathContext, PathSegment, ResultExt};
+
+struct User {
+    email: &'static str,
+}
+
+fn validate_user(user: &User) -> Result<(), Error> {
+    ensure!(user.email.contains('@'), "invalid address");
+    Ok(())
+}
+
+fn validate_users(users: &[User]) -> Result<(), Error> {
+    for (i, user) in users.iter().enumerate() {
+        validate_user(user).at("email").at(i)?;
+    }
+    Ok(())
+}
+
+fn validate_config(users: &[User]) -> Result<(), Error> {
+    validate_users(users).at("users")
+}
+
+#[test]
+fn path_is_collapsed() {
+    let users = [User { email: "a@example.com" }, User { email: "nobody" }];
+    let err = validate_config(&users).unwrap_err();
+    assert_eq!(err.to_string(), "users[1].email");
+    assert_eq!(err.name(), Some("failure::PathContext"));
+    assert_eq!(err.iter_chain().count(), 2);
+    assert_eq!(err.find_root_cause().to_string(), "invalid address");
+
+    let path = err.data_path().unwrap();
+    assert_eq!(path.to_json_pointer(), "/users/1/email");
+    assert_eq!(path.segments(), &[
+        PathSegment::from("users"),
+        PathSegment::Index(1),
+        PathSegment::from("email"),
+    ]);
+
+    let context = err.downcast::<PathContext>().unwrap();
+    assert_eq!(context.into_error().to_string(), "invalid address");
+}
+
+#[test]
+fn path_below_other_context() {
+    let users = [User { email: "nobody" }];
+    let err = validate_config(&users).context("loading config").unwrap_err();
+    let err = Error::from(err);
+    assert_eq!(err.to_string(), "loading config");
+    assert_eq!(err.data_path().unwrap().to_string(), "users[0].email");
+
+    // A segment added above the context starts a new `PathContext`, and the
+    // paths are joined.
+    let err = err.at("server");
+    assert_eq!(err.to_string(), "server");
+    assert_eq!(err.data_path().unwrap().to_string(), "server.users[0].email");
+    assert_eq!(err.data_path().unwrap().to_json_pointer(), "/server/users/0/email");
+    assert_eq!(err.iter_chain().count(), 4);
+}
+
+#[test]
+fn failure_at_path() {
+    let err = "x".parse::<u8>().at("port").unwrap_err();
+    assert_eq!(err.data_path().unwrap().to_json_pointer(), "/port");
+    assert!(err.iter_causes().next().unwrap().downcast_ref::<std::num::ParseIntError>().is_some());
+    assert!(format_err!("no data").data_path().is_none());
+}
@@ -0,0 +1,131 @@
+#![cfg(all(feature = "std", feature = "derive"))]
+
+#[macro_use]
+extern crate failure;
+
+use failure::{AssertionFailure, Error, Fail};
+
+fn assertion(result: Result<(), Error>) -> AssertionFailure {
+    result.unwrap_err().downcast::<AssertionFailure>().unwrap()
+}
+
+#[test]
+fn ensure_eq_captures_operands() {
+    fn check(len: usize) -> Result<(), Error> {
+        let expected = [1, 2];
+        ensure_eq!(len, expected.len());
+        Ok(())
+    }
+
+    assert!(check(2).is_ok());
+    let failure = assertion(check(3));
+    assert_eq!(failure.expression(), "len == expected.len()");
+    assert_eq!(failure.left(), Some("3"));
+    assert_eq!(failure.right(), Some("2"));
+    assert_eq!(failure.message(), None);
+    assert!(failure.file().ends_with("ensure.rs"));
+    assert_eq!(failure.line(), 16);
+    assert_eq!(failure.to_string(), "assertion failed: `len == expected.len()` (left: `3`, right: `2`)");
+}
+
+#[test]
+fn ensure_ne_with_message() {
+    fn check(name: &str) -> Result<(), Error> {
+        ensure_ne!(name, "root", "user {} is reserved", name);
+        Ok(())
+    }
+
+    assert!(check("alice").is_ok());
+    let failure = assertion(check("root"));
+    assert_eq!(failure.expression(), "name != \"root\"");
+    assert_eq!(failure.message(), Some("user root is reserved"));
+    assert_eq!(failure.to_string(), "user root is reserved (left: `\"root\"`, right: `\"root\"`)");
+}
+
+#[test]
+fn ensure_matches_captures_value() {
+    fn check(value: Option<u32>) -> Result<(), Error> {
+        ensure_matches!(value, Some(n) if n > 3);
+        Ok(())
+    }
+
+    assert!(check(Some(4)).is_ok());
+    let failure = assertion(check(Some(2)));
+    assert_eq!(failure.expression(), "value matches Some(n) if n > 3");
+    assert_eq!(failure.left(), Some("Some(2)"));
+    assert_eq!(failure.right(), None);
+}
+
+#[test]
+fn ensure_splits_comparisons() {
+    fn check(a: u32, b: u32) -> Result<(), Error> {
+        ensure!(a + 1 <= b * 2);
+        Ok(())
+    }
+
+    assert!(check(1, 1).is_ok());
+    let failure = assertion(check(4, 2));
+    assert_eq!(failure.expression(), "a + 1 <= b * 2");
+    assert_eq!(failure.left(), Some("5"));
+    assert_eq!(failure.right(), Some("4"));
+}
+
+#[test]
+fn ensure_keeps_other_conditions() {
+    fn check(a: u32, b: bool) -> Result<(), Error> {
+        ensure!(a == 1 || b);
+        ensure!(Vec::<u32>::new().len() as u32 == a);
+        Ok(())
+    }
+
+    assert_eq!(check(2, false).unwrap_err().to_string(), "a == 1 || b");
+    assert_eq!(
+        check(1, true).unwrap_err().to_string(),
+        "Vec::<u32>::new().len() as u32 == a"
+    );
+}
+
+#[test]
+fn operands_without_debug() {
+    struct Opaque(u32);
+
+    impl PartialEq for Opaque {
+        fn eq(&self, other: &Opaque) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    fn check(a: Opaque) -> Result<(), Error> {
+        ensure!(a == Opaque(1));
+        Ok(())
+    }
+
+    let failure = assertion(check(Opaque(2)));
+    assert_eq!(failure.left(), None);
+    assert_eq!(failure.to_string(), "assertion failed: `a == Opaque(1)`");
+}
+
+#[derive(Debug, Fail, PartialEq)]
+enum ConfigError {
+    #[fail(display = "{}", _0)]
+    Invalid(AssertionFailure),
+}
+
+impl From<AssertionFailure> for ConfigError {
+    fn from(failure: AssertionFailure) -> ConfigError {
+        ConfigError::Invalid(failure)
+    }
+}
+
+#[test]
+fn concrete_error_type() {
+    fn check(threads: u32) -> Result<(), ConfigError> {
+        ensure!(threads > 0);
+        Ok(())
+    }
+
+    match check(0) {
+        Err(ConfigError::Invalid(failure)) => assert_eq!(failure.left(), Some("0")),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
@@ -0,0 +1,34 @@
+#![cfg(feature = "std")]
+
+extern crate failure;
+
+use std::env;
+use std::fs;
+use std::process::Command;
+
+use failure::Explanations;
+
+#[test]
+fn failure_explain_binary() {
+    let dir = env::temp_dir().join(format!("failure-explain-bin-{}", std::process::id()));
+    let mut explanations = Explanations::new();
+    explanations.insert("E1042", "The configuration file could not be parsed.\n");
+    explanations.write_dir(&dir).unwrap();
+
+    let bin = env!("CARGO_BIN_EXE_failure-explain");
+    let output = Command::new(bin).arg("--dir").arg(&dir).arg("E1042").output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "The configuration file could not be parsed.\n"
+    );
+
+    let output = Command::new(bin)
+        .arg("E9999")
+        .env("FAILURE_EXPLAIN_DIR", &dir)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,4 +1,5 @@
-#[macro_use]
+#![cfg(all(feature = "std", feature = "derive"))]
+
 extern crate failure;
 
 use failure::Fail;
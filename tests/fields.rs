@@ -0,0 +1,71 @@
+#![cfg(feature = "std")]
+
+extern crate failure;
+
+use std::io;
+
+use failure::{Error, Fail, JsonReport, ResultExt, Value, ValueRef};
+
+fn open(path: &'static str) -> Result<(), io::Error> {
+    Err(io::Error::new(io::ErrorKind::NotFound, path))
+}
+
+fn load() -> Result<(), Error> {
+    open("/etc/app.toml").context_with("reading config", &[("path", Value::from("/etc/app.toml"))])?;
+    Ok(())
+}
+
+fn start(attempt: u32) -> Result<(), Error> {
+    load().map_err(|err| err.context("starting app").field("attempt", attempt))?;
+    Ok(())
+}
+
+#[test]
+fn fields_in_chain() {
+    let err = start(3).unwrap_err();
+    let fields: Vec<_> = err.fields().map(|field| (field.key(), field.value().get())).collect();
+    assert_eq!(
+        fields,
+        vec![("attempt", ValueRef::U64(3)), ("path", ValueRef::Str("/etc/app.toml"))]
+    );
+    assert_eq!(err.to_string(), "starting app");
+}
+
+#[test]
+fn fields_in_debug() {
+    let err = start(3).unwrap_err();
+    let debug = format!("{:?}", err.as_fail());
+    assert!(debug.ends_with("starting app (attempt=3)"), "{}", debug);
+}
+
+#[test]
+fn mixed_values() {
+    let err = open("x")
+        .context_with("reading", &[("path", Value::from("x")), ("retry", Value::from(false))])
+        .unwrap_err();
+    assert_eq!(err.fields()[1].value().get(), ValueRef::Bool(false));
+}
+
+#[test]
+fn fields_in_json_report() {
+    let err = start(3).unwrap_err();
+    let io_error = std::any::type_name::<io::Error>();
+    let expected = format!(
+        concat!(
+            r#"{{"chain":[{{"message":"starting app","name":"failure::Context","fields":{{"attempt":3}}}},"#,
+            r#"{{"message":"reading config","name":"failure::Context","fields":{{"path":"/etc/app.toml"}}}},"#,
+            r#"{{"message":"/etc/app.toml","name":"{0}","fields":{{}}}}]}}"#,
+        ),
+        io_error
+    );
+    assert_eq!(JsonReport::new(&err).to_string(), expected);
+}
+
+#[test]
+fn json_report_escapes_strings() {
+    let err: Error = failure::Context::new("line 1\n\"quoted\"").field("tab", "a\tb").into();
+    assert_eq!(
+        JsonReport::new(&err).to_string(),
+        r#"{"chain":[{"message":"line 1\n\"quoted\"","name":"failure::Context","fields":{"tab":"a\tb"}}]}"#
+    );
+}
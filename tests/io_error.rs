@@ -0,0 +1,71 @@
+#![cfg(all(feature = "std", feature = "derive"))]
+
+#[macro_use]
+extern crate failure;
+
+use std::io::{self, Read};
+
+use failure::{Error, Fallible, ResultExt};
+
+#[derive(Debug, Fail)]
+#[fail(display = "frame {} is corrupt", _0)]
+struct CorruptFrame(u32);
+
+struct Decoder;
+
+impl Decoder {
+    fn decode(&mut self, _: &mut [u8]) -> Fallible<usize> {
+        Err(CorruptFrame(4)).context("decoding block")?
+    }
+}
+
+impl Read for Decoder {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.decode(buf).map_err(|err| err.into_io_error(io::ErrorKind::InvalidData))
+    }
+}
+
+fn read_all(mut reader: impl Read) -> Fallible<Vec<u8>> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+#[test]
+fn round_trip_gives_original_error() {
+    let err = read_all(Decoder).unwrap_err();
+    assert_eq!(err.to_string(), "decoding block");
+    assert_eq!(err.iter_chain().count(), 2);
+    assert!(err.find_root_cause().downcast_ref::<CorruptFrame>().is_some());
+    assert_eq!(err.io_error_kind(), None);
+}
+
+#[test]
+fn io_error_from_error() {
+    let err: Error = io::Error::from_raw_os_error(2).into();
+    let err = Error::from(err.context("opening config"));
+    assert_eq!(err.io_error_kind(), Some(io::ErrorKind::NotFound));
+    assert_eq!(err.raw_os_error(), Some(2));
+
+    // The kind is taken from the chain.
+    let io_error = io::Error::from(err);
+    assert_eq!(io_error.kind(), io::ErrorKind::NotFound);
+    assert_eq!(io_error.to_string(), "opening config");
+    let err = Error::from(io_error);
+    assert_eq!(err.to_string(), "opening config");
+
+    // A plain `io::Error` is returned as itself.
+    let err: Error = io::Error::from_raw_os_error(2).into();
+    assert_eq!(io::Error::from(err).raw_os_error(), Some(2));
+
+    let io_error = io::Error::from(Error::from(CorruptFrame(1)));
+    assert_eq!(io_error.kind(), io::ErrorKind::Other);
+}
+
+#[test]
+fn other_io_errors_are_wrapped() {
+    let err = Error::from(io::Error::new(io::ErrorKind::TimedOut, "read timed out"));
+    assert!(err.downcast_ref::<io::Error>().is_some());
+    assert_eq!(err.io_error_kind(), Some(io::ErrorKind::TimedOut));
+    assert_eq!(err.raw_os_error(), None);
+}
@@ -0,0 +1,135 @@
+#![cfg(feature = "std")]
+
+extern crate failure;
+
+use std::fmt;
+use std::rc::Rc;
+
+use failure::{Error, Fail, LocalContext, LocalError, LocalFail, LocalStdError};
+
+#[derive(Debug)]
+struct Shared {
+    name: Rc<String>,
+}
+
+impl fmt::Display for Shared {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} is unavailable", self.name)
+    }
+}
+
+impl LocalFail for Shared {}
+
+#[derive(Debug)]
+struct Timeout;
+
+impl fmt::Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "timed out")
+    }
+}
+
+impl Fail for Timeout {}
+
+#[derive(Debug)]
+struct RcStdError(Rc<u8>);
+
+impl fmt::Display for RcStdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "error {}", self.0)
+    }
+}
+
+impl std::error::Error for RcStdError {}
+
+fn shared() -> Shared {
+    Shared { name: Rc::new(String::from("cache")) }
+}
+
+#[test]
+fn local_error_from_local_fail() {
+    let err = LocalError::from(shared());
+    assert_eq!(err.to_string(), "cache is unavailable");
+    assert!(err.downcast_ref::<Shared>().is_some());
+    assert!(err.downcast_ref::<Error>().is_none());
+    assert_eq!(*err.downcast::<Shared>().unwrap().name, "cache");
+}
+
+#[test]
+fn local_context_chain() {
+    let err = LocalError::from(shared().local_context("loading config"));
+    let chain: Vec<String> = err.iter_chain().map(|fail| fail.to_string()).collect();
+    assert_eq!(chain, ["loading config", "cache is unavailable"]);
+    assert!(err.find_root_cause().downcast_ref::<Shared>().is_some());
+    assert_eq!(
+        err.downcast_ref::<LocalContext<&str>>().map(|ctx| *ctx.get_context()),
+        Some("loading config")
+    );
+}
+
+#[test]
+fn local_error_from_std() {
+    let err = LocalError::from_std(RcStdError(Rc::new(7)));
+    assert_eq!(err.to_string(), "error 7");
+    let wrapped = err.downcast_ref::<LocalStdError<RcStdError>>().unwrap();
+    assert_eq!(*wrapped.get_ref().0, 7);
+}
+
+#[test]
+fn error_round_trip() {
+    let err = Error::from(Timeout).context("fetching index");
+    let local = LocalError::from(Error::from(err));
+    assert_eq!(local.iter_causes().count(), 1);
+    assert_eq!(local.to_string(), "fetching index");
+    let err = local.downcast_ref::<Error>().unwrap();
+    assert!(err.find_root_cause().downcast_ref::<Timeout>().is_some());
+    assert!(local.find_root_cause().downcast_ref::<Timeout>().is_some());
+
+    let err = local.into_error().unwrap();
+    assert_eq!(err.to_string(), "fetching index");
+    assert!(err.find_root_cause().downcast_ref::<Timeout>().is_some());
+}
+
+#[test]
+fn error_downcasts_by_value() {
+    let local = LocalError::from(Error::from(Timeout));
+    let local = local.downcast::<Shared>().unwrap_err();
+    let err = local.downcast::<Error>().unwrap();
+    assert!(err.downcast::<Timeout>().is_ok());
+}
+
+#[test]
+fn into_error_fails_with_local_context() {
+    let local = LocalError::from(Error::from(Timeout)).context("retrying");
+    let local = LocalError::from(local);
+    let local = local.into_error().unwrap_err();
+    assert_eq!(local.to_string(), "retrying");
+    let err = local.iter_causes().next().unwrap().downcast_ref::<Error>().unwrap();
+    assert!(err.downcast_ref::<Timeout>().is_some());
+}
+
+#[test]
+fn chain_crosses_into_error() {
+    let err = Error::from(Timeout).wrap("fetching index").context("updating registry");
+    let local = LocalError::from(LocalError::from(Error::from(err)).context("loading config"));
+    let chain: Vec<String> = local.iter_chain().map(|fail| fail.to_string()).collect();
+    assert_eq!(chain, ["loading config", "updating registry", "fetching index", "timed out"]);
+
+    let mut causes = local.iter_causes();
+    assert!(causes.next().unwrap().downcast_ref::<Error>().is_some());
+    let context = causes.next().unwrap().downcast_ref::<failure::Context<&str>>().unwrap();
+    assert_eq!(*context.get_context(), "fetching index");
+    let root = local.find_root_cause();
+    assert!(root.downcast_ref::<Timeout>().is_some());
+    assert_eq!(root.local_name(), None);
+    assert!(root.local_backtrace().is_none());
+}
+
+#[test]
+fn fail_methods_are_not_ambiguous() {
+    use failure::*;
+
+    assert_eq!(Timeout.name(), None);
+    let io_err = std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out");
+    assert!(io_err.cause().is_none());
+}
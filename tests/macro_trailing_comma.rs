@@ -1,3 +1,6 @@
+#![cfg(feature = "std")]
+#![allow(clippy::diverging_sub_expression)]
+
 #[macro_use]
 extern crate failure;
 
@@ -50,10 +53,23 @@ fn single_arg_ensure() {
         Err("false".to_string()));
     assert_eq!(
         wrap_early_return!(ensure!(true == false)),
-        Err("true == false".to_string()));
+        Err("assertion failed: `true == false` (left: `true`, right: `false`)".to_string()));
     assert_eq!(
         wrap_early_return!(ensure!(4 == 5)),
-        Err("4 == 5".to_string()));
+        Err("assertion failed: `4 == 5` (left: `4`, right: `5`)".to_string()));
+}
+
+#[test]
+fn ensure_eq() {
+    assert_eq!(
+        wrap_early_return!(ensure_eq!(4, 5)),
+        wrap_early_return!(ensure_eq!(4, 5,)));
+    assert_eq!(
+        wrap_early_return!(ensure_eq!(4, 5, "test {}", 4)),
+        wrap_early_return!(ensure_eq!(4, 5, "test {}", 4,)));
+    assert_eq!(
+        wrap_early_return!(ensure_matches!(Some(4), None)),
+        wrap_early_return!(ensure_matches!(Some(4), None,)));
 }
 
 #[test]
@@ -0,0 +1,121 @@
+#![cfg(feature = "std")]
+
+extern crate failure;
+
+use std::panic::{self, AssertUnwindSafe};
+use std::thread;
+
+use failure::{catch_panic, err_msg, Error, JoinHandleExt, PanicFailure};
+
+#[test]
+fn catch_panic_with_message() {
+    assert_eq!(catch_panic(|| 4).unwrap(), 4);
+
+    let line = line!() + 1;
+    let err = catch_panic(|| -> () { panic!("static message") }).unwrap_err();
+    let panic = err.downcast_ref::<PanicFailure>().unwrap();
+    assert_eq!(panic.message(), Some("static message"));
+    let location = panic.location().unwrap();
+    assert_eq!(location.file(), file!());
+    assert_eq!(location.line(), line);
+    assert_eq!(
+        err.to_string(),
+        format!("panicked at 'static message', {}:{}:{}", file!(), line, location.column()),
+    );
+    assert_eq!(err.name(), Some("failure::PanicFailure"));
+
+    let id = 7;
+    let err = catch_panic(|| -> () { panic!("worker {} failed", id) }).unwrap_err();
+    assert_eq!(err.downcast_ref::<PanicFailure>().unwrap().message(), Some("worker 7 failed"));
+}
+
+#[test]
+fn catch_panic_with_payload() {
+    let err = catch_panic(|| panic::panic_any(err_msg("disk full"))).unwrap_err();
+    let panic = err.downcast_ref::<PanicFailure>().unwrap();
+    assert_eq!(panic.message(), None);
+    assert_eq!(panic.error().unwrap().to_string(), "disk full");
+    assert_eq!(err.iter_chain().count(), 2);
+
+    let compat = err_msg("disk full").compat();
+    let err = catch_panic(AssertUnwindSafe(|| panic::panic_any(compat))).unwrap_err();
+    assert_eq!(err.find_root_cause().to_string(), "disk full");
+
+    let err = catch_panic(|| panic::panic_any(42)).unwrap_err();
+    assert!(err.to_string().starts_with("panicked with a payload of an opaque type, "));
+}
+
+#[test]
+fn nested_catch_panic() {
+    let outer = catch_panic(|| {
+        let inner = catch_panic(|| -> () { panic!("inner") }).unwrap_err();
+        assert!(inner.downcast_ref::<PanicFailure>().unwrap().location().is_some());
+        panic!("outer");
+    }).unwrap_err();
+    assert_eq!(outer.downcast_ref::<PanicFailure>().unwrap().message(), Some("outer"));
+}
+
+#[test]
+fn join_handle() {
+    let handle = thread::spawn(|| 1 + 1);
+    assert_eq!(handle.join_failure().unwrap(), 2);
+
+    let handle = thread::spawn(|| catch_panic(|| -> () { panic!("in worker") }));
+    let err: Error = handle.join_failure().unwrap().unwrap_err();
+    assert!(err.downcast_ref::<PanicFailure>().unwrap().location().is_some());
+}
+
+#[test]
+fn panic_hook_prints_chain() {
+    use std::env;
+    use std::process::Command;
+
+    // The hook is process-wide, so it is tested in a child process running
+    // only this test.
+    if env::var_os("FAILURE_PANIC_HOOK_CHILD").is_some() {
+        failure::install_panic_hook();
+        panic::panic_any(err_msg("disk full").wrap("writing index"));
+    }
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["panic_hook_prints_chain", "--exact", "--nocapture"])
+        .env("FAILURE_PANIC_HOOK_CHILD", "1")
+        .env("RUST_FAILURE_BACKTRACE", "0")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&format!("panicked at 'writing index', {}:", file!())),
+        "{}", stderr,
+    );
+    assert!(stderr.contains("\ncaused by: disk full\n"), "{}", stderr);
+}
+
+#[test]
+fn panic_caught_inside_catch_panic_is_printed() {
+    use std::env;
+    use std::process::Command;
+
+    if env::var_os("FAILURE_CAUGHT_PANIC_CHILD").is_some() {
+        let line = line!() + 3;
+        let err = catch_panic(|| {
+            let _ = panic::catch_unwind(|| -> () { panic!("caught inside") });
+            panic!("outer");
+        }).unwrap_err();
+        assert_eq!(err.downcast_ref::<PanicFailure>().unwrap().location().unwrap().line(), line);
+        catch_panic(|| {
+            let _ = panic::catch_unwind(|| -> () { panic!("caught again") });
+        }).unwrap();
+        return;
+    }
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["panic_caught_inside_catch_panic_is_printed", "--exact", "--nocapture"])
+        .env("FAILURE_CAUGHT_PANIC_CHILD", "1")
+        .env("RUST_FAILURE_BACKTRACE", "0")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("panicked at 'caught inside', "), "{}", stderr);
+    assert!(stderr.contains("panicked at 'caught again', "), "{}", stderr);
+    assert!(!stderr.contains("'outer'"), "{}", stderr);
+}
@@ -0,0 +1,80 @@
+#![cfg(feature = "std")]
+
+#[macro_use]
+extern crate failure;
+
+use std::cell::Cell;
+use std::fmt;
+
+use failure::{err_msg, retry, Backoff, Error, Fail, RetryHistory};
+
+#[derive(Debug)]
+struct Unavailable(u32);
+
+impl fmt::Display for Unavailable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "service unavailable on attempt {}", self.0)
+    }
+}
+
+impl Fail for Unavailable {
+    fn is_transient(&self) -> Option<bool> {
+        Some(true)
+    }
+}
+
+#[test]
+fn retries_until_success() {
+    let calls = Cell::new(0);
+    let value = retry(&Backoff::none().max_attempts(5), || {
+        calls.set(calls.get() + 1);
+        if calls.get() < 3 {
+            return Err(Unavailable(calls.get()));
+        }
+        Ok(calls.get())
+    }).unwrap();
+    assert_eq!(value, 3);
+}
+
+#[test]
+fn gives_up_with_history() {
+    let calls = Cell::new(0);
+    let err = retry(&Backoff::fixed(Default::default()), || -> Result<(), Error> {
+        calls.set(calls.get() + 1);
+        Err(Unavailable(calls.get()).into())
+    }).unwrap_err();
+    assert_eq!(calls.get(), 3);
+    assert_eq!(err.to_string(), "service unavailable on attempt 3");
+    assert!(err.downcast_ref::<Unavailable>().is_some());
+    assert!(err.is_transient());
+    assert_eq!(err.iter_chain().count(), 1);
+
+    let history = RetryHistory::find(&err).unwrap();
+    assert_eq!(history.attempts(), 3);
+    assert_eq!(err.suppressed()[0].to_string(), "failed after 3 attempts");
+    let earlier: Vec<_> = history.errors().iter().map(|e| e.to_string()).collect();
+    assert_eq!(earlier, ["service unavailable on attempt 1", "service unavailable on attempt 2"]);
+}
+
+#[test]
+fn permanent_errors_are_not_retried() {
+    let calls = Cell::new(0);
+    let err = retry(&Backoff::none(), || -> Result<(), Error> {
+        calls.set(calls.get() + 1);
+        bail!("invalid request")
+    }).unwrap_err();
+    assert_eq!(calls.get(), 1);
+    assert_eq!(err.to_string(), "invalid request");
+    assert!(RetryHistory::find(&err).is_none());
+
+    let calls = Cell::new(0);
+    let err = retry(&Backoff::none(), || -> Result<(), Error> {
+        calls.set(calls.get() + 1);
+        if calls.get() == 1 {
+            return Err(Unavailable(1).into());
+        }
+        Err(err_msg("invalid request"))
+    }).unwrap_err();
+    assert_eq!(calls.get(), 2);
+    assert_eq!(RetryHistory::find(&err).unwrap().attempts(), 2);
+}
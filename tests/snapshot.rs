@@ -0,0 +1,115 @@
+#![cfg(feature = "std")]
+
+extern crate failure;
+
+use std::cell::Cell;
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+use std::rc::Rc;
+
+use failure::{Error, Fail, Snapshot, SnapshotExt};
+
+#[derive(Debug)]
+struct Counted<'a> {
+    formatted: &'a Cell<u32>,
+    source: Option<io_like::Timeout>,
+}
+
+mod io_like {
+    use std::error::Error as StdError;
+    use std::fmt;
+
+    #[derive(Debug)]
+    pub struct Timeout;
+
+    impl fmt::Display for Timeout {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "timed out")
+        }
+    }
+
+    impl StdError for Timeout {}
+}
+
+impl<'a> fmt::Display for Counted<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.formatted.set(self.formatted.get() + 1);
+        write!(f, "request failed")
+    }
+}
+
+impl<'a> StdError for Counted<'a> {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source.as_ref().map(|source| source as &(dyn StdError + 'static))
+    }
+}
+
+#[test]
+fn formatted_once() {
+    let formatted = Cell::new(0);
+    let snapshot = {
+        let error = Counted { formatted: &formatted, source: Some(io_like::Timeout) };
+        Snapshot::new(&error)
+    };
+    assert_eq!(formatted.get(), 1);
+
+    for _ in 0..3 {
+        assert_eq!(snapshot.to_string(), "request failed");
+    }
+    assert_eq!(formatted.get(), 1);
+    assert!(snapshot.debug().starts_with("Counted { formatted: Cell { value: "));
+    assert!(snapshot.debug().ends_with("source: Some(Timeout) }"));
+    assert_eq!(format!("{:?}", snapshot), snapshot.debug());
+    assert_eq!(format!("{:>16}", snapshot.cause().unwrap()), "       timed out");
+}
+
+#[test]
+fn chain_and_clone() {
+    let formatted = Cell::new(0);
+    let result = Err::<(), _>(Counted { formatted: &formatted, source: Some(io_like::Timeout) });
+    let snapshot = result.snapshot_err().unwrap_err();
+    assert_eq!(snapshot.name(), Some(std::any::type_name::<Counted>()));
+
+    let err = Error::from(snapshot.clone());
+    let chain: Vec<_> = err.iter_chain().map(|fail| (fail.to_string(), fail.name())).collect();
+    assert_eq!(chain, [
+        ("request failed".to_string(), snapshot.name()),
+        ("timed out".to_string(), None),
+    ]);
+    assert_eq!(err.find_root_cause().downcast_ref::<Snapshot>().unwrap().debug(), "Timeout");
+    assert_eq!(snapshot.message(), "request failed");
+}
+
+#[derive(Debug)]
+struct ScriptError {
+    script: Rc<str>,
+    source: io::Error,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "script `{}` failed", self.script)
+    }
+}
+
+impl StdError for ScriptError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.source)
+    }
+}
+
+#[test]
+fn io_error_kind_survives_clone() {
+    let snapshot = {
+        let source = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+        Snapshot::new(&ScriptError { script: Rc::from("x +"), source })
+    };
+    let cause = snapshot.cause().unwrap().downcast_ref::<Snapshot>().unwrap();
+    assert_eq!(cause.io_error_kind(), Some(io::ErrorKind::TimedOut));
+    assert_eq!(snapshot.io_error_kind(), None);
+
+    let err = Error::from(snapshot.clone());
+    assert_eq!(err.io_error_kind(), Some(io::ErrorKind::TimedOut));
+    assert_eq!(err.find_kind::<io::ErrorKind>(), Some(io::ErrorKind::TimedOut));
+}
@@ -0,0 +1,140 @@
+#![cfg(feature = "std")]
+
+extern crate failure;
+
+use std::cell::Cell;
+use std::io;
+
+use failure::{catch_panic, err_msg, CleanupGuard, Error, ErrorMessage, JsonReport};
+
+fn rollback(fails: bool) -> Result<(), io::Error> {
+    if fails {
+        Err(io::Error::new(io::ErrorKind::Other, "rollback failed"))
+    } else {
+        Ok(())
+    }
+}
+
+#[test]
+fn suppressed_errors() {
+    let err = err_msg("commit failed")
+        .with_suppressed(rollback(true).unwrap_err())
+        .with_suppressed(err_msg("unlock failed"));
+    assert_eq!(err.to_string(), "commit failed");
+    assert_eq!(err.iter_chain().count(), 1);
+    assert_eq!(err.suppressed().len(), 2);
+    assert!(err.suppressed()[0].downcast_ref::<io::Error>().is_some());
+
+    let debug = format!("{:?}", err);
+    assert!(debug.contains("\n\nSuppressed: Custom { kind: Other, error: \"rollback failed\" }"));
+    assert!(debug.contains("\n\nSuppressed: ErrorMessage { msg: \"unlock failed\" }"));
+}
+
+#[test]
+fn suppressed_survive_wrap() {
+    let err = err_msg("commit failed")
+        .with_suppressed(err_msg("rollback failed"))
+        .wrap("saving");
+    assert_eq!(err.suppressed().len(), 1);
+}
+
+#[test]
+fn suppressed_in_json_report() {
+    let err = err_msg("commit failed")
+        .with_suppressed(err_msg("rollback failed").with_suppressed(err_msg("unlock failed")));
+    assert_eq!(
+        JsonReport::new(&err).to_string(),
+        concat!(
+            r#"{"chain":[{"message":"commit failed","name":"failure::ErrorMessage","fields":{}}],"#,
+            r#""suppressed":[{"chain":[{"message":"rollback failed","name":"failure::ErrorMessage","fields":{}}],"#,
+            r#""suppressed":[{"chain":[{"message":"unlock failed","name":"failure::ErrorMessage","fields":{}}]}]}]}"#,
+        ),
+    );
+}
+
+#[test]
+fn downcast_with_suppressed() {
+    let err = err_msg("commit failed").with_suppressed(err_msg("rollback failed"));
+    let (message, suppressed) = err.downcast_with_suppressed::<ErrorMessage>().unwrap();
+    assert_eq!(message.to_string(), "commit failed");
+    assert_eq!(suppressed.len(), 1);
+
+    let err = err_msg("commit failed").with_suppressed(err_msg("rollback failed"));
+    let err = err.downcast_with_suppressed::<io::Error>().unwrap_err();
+    assert_eq!(err.suppressed().len(), 1);
+}
+
+#[test]
+fn cleanup_guard() {
+    let guard = CleanupGuard::new(|| rollback(true));
+    let err = guard.finish(Err::<(), _>(err_msg("transfer failed"))).unwrap_err();
+    assert_eq!(err.to_string(), "transfer failed");
+    assert_eq!(err.suppressed()[0].to_string(), "rollback failed");
+
+    let guard = CleanupGuard::new(|| rollback(true));
+    let err = guard.finish(Ok::<_, Error>(())).unwrap_err();
+    assert_eq!(err.to_string(), "rollback failed");
+    assert!(err.suppressed().is_empty());
+
+    let guard = CleanupGuard::new(|| rollback(false));
+    assert_eq!(guard.finish(Ok::<_, Error>(4)).unwrap(), 4);
+}
+
+#[test]
+fn cleanup_guard_drop_and_cancel() {
+    let ran = Cell::new(0);
+    {
+        let _guard = CleanupGuard::new(|| {
+            ran.set(ran.get() + 1);
+            rollback(true)
+        });
+    }
+    assert_eq!(ran.get(), 1);
+
+    let guard = CleanupGuard::new(|| {
+        ran.set(ran.get() + 1);
+        rollback(false)
+    });
+    guard.cancel();
+    assert_eq!(ran.get(), 1);
+}
+
+#[test]
+fn cleanup_guard_dropped_in_run() {
+    let err = CleanupGuard::new(|| rollback(false))
+        .run(|| -> Result<(), Error> {
+            let _guard = CleanupGuard::new(|| rollback(true));
+            Err(err_msg("transfer failed"))
+        })
+        .unwrap_err();
+    assert_eq!(err.to_string(), "transfer failed");
+    assert_eq!(err.suppressed()[0].to_string(), "rollback failed");
+
+    let err = CleanupGuard::new(|| rollback(false))
+        .run(|| -> Result<(), Error> {
+            let _guard = CleanupGuard::new(|| rollback(true));
+            Ok(())
+        })
+        .unwrap_err();
+    assert_eq!(err.to_string(), "rollback failed");
+}
+
+#[test]
+fn cleanup_guard_dropped_in_panic() {
+    let err = catch_panic(|| {
+        let _guard = CleanupGuard::new(|| rollback(true));
+        panic!("transfer failed");
+    }).unwrap_err();
+    assert!(err.to_string().starts_with("panicked at 'transfer failed'"));
+    assert_eq!(err.suppressed()[0].to_string(), "rollback failed");
+
+    // A failure outside of the panic is left to the enclosing `run`.
+    let err = CleanupGuard::new(|| rollback(false))
+        .run(|| {
+            catch_panic(|| {
+                let _guard = CleanupGuard::new(|| rollback(true));
+            })
+        })
+        .unwrap_err();
+    assert_eq!(err.to_string(), "rollback failed");
+}
@@ -10,6 +10,8 @@ test_failure_in() {
   cd $1
   cargo_test
   cargo_test --no-default-features
+  cargo check --no-default-features --features alloc || { exit 101; }
+  cargo_test --no-default-features --features alloc --lib
   cargo_test --features backtrace
   test_derive_in "$1/failure_derive"
   cd $DIR